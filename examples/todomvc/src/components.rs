@@ -40,16 +40,14 @@ pub async fn todo_list(
         section .todoapp {
             header .header {
                 h1 { "todos" }
-                form
-                    hx-post=(url.clone().for_component(CreateTodo).build())
-                    hx-target="#todo-container"
-                    hx-swap="innerHTML"
-                    hx-on--after-request="this.reset()" {
-                    input .new-todo
-                        placeholder="What needs to be done?"
-                        name="title"
-                        autofocus;
-                }
+                (hx::post(url.clone().for_component(CreateTodo))
+                    .attr("hx-on--after-request", "this.reset()")
+                    .form(html! {
+                        input .new-todo
+                            placeholder="What needs to be done?"
+                            name="title"
+                            autofocus;
+                    }))
             }
 
             (render_todo_container(&state, &url, &db))
@@ -58,7 +56,7 @@ pub async fn todo_list(
 }
 
 // Just the todo container (for filter updates)
-#[component(path = "/todo_container")]
+#[component(path = "/todo_container", root = "todo-container")]
 pub async fn todo_container(
     state: TodoViewState,
     url: UrlBuilder,
@@ -79,13 +77,13 @@ fn render_todo_container(state: &TodoViewState, url: &UrlBuilder, db: &TodoDb) -
         div #todo-container {
             @if !todos.todos.is_empty() {
             section #todo-list .main {
-                input #toggle-all .toggle-all
-                    type="checkbox"
-                    checked[all_completed]
-                    hx-post=(url.clone().for_component(ToggleAll).build())
-                    hx-target="#todo-container"
-                    hx-swap="innerHTML"
-                    hx-vals=(format!(r#"{{"completed":{}}}"#, !all_completed));
+                (hx::post(url.clone().for_component(ToggleAll))
+                    .vals(htmoxide::serde_json::json!({ "completed": !all_completed }))
+                    .id("toggle-all")
+                    .class("toggle-all")
+                    .attr("type", "checkbox")
+                    .flag("checked", all_completed)
+                    .input())
                 label for="toggle-all" { "Mark all as complete" }
 
                 ul .todo-list {
@@ -105,44 +103,20 @@ fn render_todo_container(state: &TodoViewState, url: &UrlBuilder, db: &TodoDb) -
 
                 ul .filters {
                     li {
-                        a .{@if state.filter.is_empty() { "selected" }}
-                            href=(url.clone().with_params([("filter", "")]).build_main_url())
-                            hx-get=(url.clone().for_component(TodoContainer).with_params([("filter", "")]).build())
-                            hx-target="#todo-container"
-                            hx-swap="outerHTML"
-                            hx-push-url=(url.clone().with_params([("filter", "")]).build_main_url()) {
-                            "All"
-                        }
+                        (filter_link(url, "", state.filter.is_empty()).a("All"))
                     }
                     li {
-                        a .{@if state.filter == "active" { "selected" }}
-                            href=(url.clone().with_params([("filter", "active")]).build_main_url())
-                            hx-get=(url.clone().for_component(TodoContainer).with_params([("filter", "active")]).build())
-                            hx-target="#todo-container"
-                            hx-swap="outerHTML"
-                            hx-push-url=(url.clone().with_params([("filter", "active")]).build_main_url()) {
-                            "Active"
-                        }
+                        (filter_link(url, "active", state.filter == "active").a("Active"))
                     }
                     li {
-                        a .{@if state.filter == "completed" { "selected" }}
-                            href=(url.clone().with_params([("filter", "completed")]).build_main_url())
-                            hx-get=(url.clone().for_component(TodoContainer).with_params([("filter", "completed")]).build())
-                            hx-target="#todo-container"
-                            hx-swap="outerHTML"
-                            hx-push-url=(url.clone().with_params([("filter", "completed")]).build_main_url()) {
-                            "Completed"
-                        }
+                        (filter_link(url, "completed", state.filter == "completed").a("Completed"))
                     }
                 }
 
                 @if completed_count > 0 {
-                    button .clear-completed
-                        hx-post=(url.clone().for_component(ClearCompleted).build())
-                        hx-target="#todo-container"
-                        hx-swap="innerHTML" {
-                        "Clear completed"
-                    }
+                    (hx::post(url.clone().for_component(ClearCompleted))
+                        .class("clear-completed")
+                        .button("Clear completed"))
                 }
             }
             }
@@ -150,6 +124,21 @@ fn render_todo_container(state: &TodoViewState, url: &UrlBuilder, db: &TodoDb) -
     }
 }
 
+// Filter link: swaps the container and pushes the page URL with the filter
+fn filter_link(url: &UrlBuilder, filter: &str, selected: bool) -> Hx {
+    let link = hx::get(
+        url.clone()
+            .for_component(TodoContainer)
+            .with_params([("filter", filter)]),
+    )
+    .push_main();
+    if selected {
+        link.class("selected")
+    } else {
+        link
+    }
+}
+
 fn render_todo(todo: &Todo, url: &UrlBuilder) -> Markup {
     let editing = todo.editing.unwrap_or(false);
 
@@ -158,35 +147,32 @@ fn render_todo(todo: &Todo, url: &UrlBuilder) -> Markup {
             data-id=(todo.id) {
 
             div .view {
-                input .toggle
-                    type="checkbox"
-                    checked[todo.completed]
-                    hx-post=(url.clone().for_component(ToggleTodo).with_path_param("id", todo.id).build())
-                    hx-target="#todo-container"
-                    hx-swap="innerHTML";
-
-                label
-                    hx-get=(url.clone().for_component(EditTodo).with_path_param("id", todo.id).build())
-                    hx-target=(format!("[data-id='{}']", todo.id))
-                    hx-swap="outerHTML" {
-                    (todo.title)
-                }
-
-                button .destroy
-                    hx-delete=(url.clone().for_component(DeleteTodo).with_path_param("id", todo.id).build())
-                    hx-target="#todo-container"
-                    hx-swap="innerHTML";
+                (hx::post(url.clone().for_component(ToggleTodo { id: todo.id }))
+                    .class("toggle")
+                    .attr("type", "checkbox")
+                    .flag("checked", todo.completed)
+                    .input())
+
+                (hx::get(url.clone().for_component(EditTodo { id: todo.id }))
+                    .target(format!("[data-id='{}']", todo.id))
+                    .swap(Swap::OuterHtml)
+                    .element("label", &[], Some(html! { (todo.title) })))
+
+                (hx::delete(url.clone().for_component(DeleteTodo { id: todo.id }))
+                    .class("destroy")
+                    .button(""))
             }
 
             @if editing {
-                form hx-post=(url.clone().for_component(UpdateTodo).with_path_param("id", todo.id).build())
-                    hx-target=(format!("[data-id='{}']", todo.id))
-                    hx-swap="outerHTML" {
-                    input .edit
-                        name="title"
-                        value=(todo.title)
-                        autofocus;
-                }
+                (hx::post(url.clone().for_component(UpdateTodo { id: todo.id }))
+                    .target(format!("[data-id='{}']", todo.id))
+                    .swap(Swap::OuterHtml)
+                    .form(html! {
+                        input .edit
+                            name="title"
+                            value=(todo.title)
+                            autofocus;
+                    }))
             }
         }
     }
@@ -198,7 +184,12 @@ fn render_single_todo(todo: &Todo, url: &UrlBuilder) -> Markup {
 }
 
 // Create a new todo
-#[component(prefix = "/todos", path = "/create", method = "POST")]
+#[component(
+    prefix = "/todos",
+    path = "/create",
+    root = "todo-container",
+    method = "POST"
+)]
 pub async fn create_todo(
     state: TodoViewState,
     url: UrlBuilder,
@@ -215,7 +206,12 @@ pub async fn create_todo(
 }
 
 // Toggle a todo's completed status
-#[component(prefix = "/todos", path = "/{id}/toggle", method = "POST")]
+#[component(
+    prefix = "/todos",
+    path = "/{id}/toggle",
+    root = "todo-container",
+    method = "POST"
+)]
 pub async fn toggle_todo(
     state: TodoViewState,
     url: UrlBuilder,
@@ -227,7 +223,12 @@ pub async fn toggle_todo(
 }
 
 // Delete a todo
-#[component(prefix = "/todos", path = "/{id}", method = "DELETE")]
+#[component(
+    prefix = "/todos",
+    path = "/{id}",
+    root = "todo-container",
+    method = "DELETE"
+)]
 pub async fn delete_todo(
     state: TodoViewState,
     url: UrlBuilder,
@@ -290,7 +291,12 @@ pub async fn update_todo(
 }
 
 // Toggle all todos
-#[component(prefix = "/todos", path = "/toggle_all", method = "POST")]
+#[component(
+    prefix = "/todos",
+    path = "/toggle_all",
+    root = "todo-container",
+    method = "POST"
+)]
 pub async fn toggle_all(
     state: TodoViewState,
     url: UrlBuilder,
@@ -302,7 +308,12 @@ pub async fn toggle_all(
 }
 
 // Clear completed todos
-#[component(prefix = "/todos", path = "/clear_completed", method = "POST")]
+#[component(
+    prefix = "/todos",
+    path = "/clear_completed",
+    root = "todo-container",
+    method = "POST"
+)]
 pub async fn clear_completed(
     state: TodoViewState,
     url: UrlBuilder,
@@ -1,4 +1,3 @@
-use axum::routing::get;
 use htmoxide::prelude::*;
 use std::sync::{Arc, Mutex};
 
@@ -6,7 +5,6 @@ mod components;
 mod pages;
 mod todos;
 
-use pages::index_page;
 use todos::TodoList as TodoListData;
 
 // In-memory database
@@ -17,10 +15,8 @@ async fn main() {
     // Create in-memory todo store
     let db = Arc::new(Mutex::new(TodoListData::default()));
 
-    let app = htmoxide::app()
-        .route("/", get(index_page))
-        .layer(axum::Extension(db))
-        .htmx();
+    // Pages and components register themselves
+    let app = htmoxide::app().layer(axum::Extension(db)).htmx();
 
     let listener = tokio::net::TcpListener::bind("127.0.0.1:3000")
         .await
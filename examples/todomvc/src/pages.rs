@@ -1,13 +1,9 @@
-use crate::TodoDb;
-use crate::components::{TodoViewState, todo_list};
-use axum::Extension;
+use crate::components::TodoList;
 use htmoxide::Page;
 use htmoxide::prelude::*;
 
-pub async fn index_page(Extension(db): Extension<TodoDb>) -> Page {
-    let view_state = TodoViewState::default();
-    let todo_list_url = UrlBuilder::new("/todo_list", "");
-
+#[page("/")]
+pub async fn index_page(ctx: RequestContext) -> Page {
     html! {
         (maud::DOCTYPE)
         html lang="en" {
@@ -20,7 +16,7 @@ pub async fn index_page(Extension(db): Extension<TodoDb>) -> Page {
                 script src="https://unpkg.com/htmx.org@2.0.3" {}
             }
             body {
-                (todo_list(view_state, todo_list_url, Extension(db)).await)
+                (render!(ctx, TodoList))
 
                 footer.info {
                     p { "Double-click to edit a todo" }
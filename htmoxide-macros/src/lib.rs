@@ -33,6 +33,20 @@ fn extract_type_name(ty: &syn::Type) -> String {
 /// - `#[component(prefix = "/api", method = "POST")]` - route /api/function_name with POST
 /// - `#[component(prefix = "/todos", path = "/{id}/toggle")]` - route /todos/{id}/toggle
 /// - `#[component(path = "/{id}")]` - explicit path (no prefix)
+/// - `#[component(persist = "cookie")]` - override state persistence ("none", "cookie", "session")
+/// - `#[component(root = "todo-container")]` - id of the element the component renders;
+///   `hx` builders target it by default
+/// - `#[component(root = "todo-list", swap = "innerHTML")]` - default swap (`outerHTML`
+///   with a root, so the component replaces itself)
+/// - `#[component(lazy)]` - `render!` embeds a placeholder loading the component after
+///   the page, see `htmoxide::lazy`
+/// - `#[component(placeholder = skeleton)]` - placeholder markup, `fn skeleton() -> Markup`
+/// - `#[component(poll = "2s", backoff = "30s")]` - the root re-requests the component,
+///   see `htmoxide::poll`
+///
+/// Each component also gets a PascalCase route type for `UrlBuilder::for_component`.
+/// Path parameters become its fields, typed after the component's `Path` extractor
+/// (`Path<usize>` or a tuple) and `String` otherwise: `ToggleTodo { id: usize }`.
 #[proc_macro_attribute]
 pub fn component(attr: TokenStream, item: TokenStream) -> TokenStream {
     let input_fn = parse_macro_input!(item as ItemFn);
@@ -40,25 +54,36 @@ pub fn component(attr: TokenStream, item: TokenStream) -> TokenStream {
     let fn_name_str = fn_name.to_string();
 
     // Parse the attribute for route configuration
-    let (route_path, http_method) = if attr.is_empty() {
+    let (route_path, http_method, args) = if attr.is_empty() {
         // Auto-generate: /function_name with GET
-        (format!("/{}", fn_name_str), "GET".to_string())
+        (
+            format!("/{}", fn_name_str),
+            "GET".to_string(),
+            ComponentArgs::default(),
+        )
     } else {
         let attr_str = attr.to_string();
 
         if attr_str.starts_with('"') {
             // Explicit path: #[component("/users")]
             let lit: LitStr = parse_macro_input!(attr as LitStr);
-            (lit.value(), "GET".to_string())
+            (lit.value(), "GET".to_string(), ComponentArgs::default())
         } else if attr_str.contains("prefix")
             || attr_str.contains("method")
             || attr_str.contains("path")
+            || attr_str.contains("persist")
+            || attr_str.contains("root")
+            || attr_str.contains("swap")
+            || attr_str.contains("lazy")
+            || attr_str.contains("placeholder")
+            || attr_str.contains("poll")
+            || attr_str.contains("backoff")
         {
             // Parse component args: #[component(prefix = "/api", method = "POST", path = "/{id}")]
             let args = parse_macro_input!(attr as ComponentArgs);
 
             // Build final path: {prefix}{path} or {prefix}/{fn_name} or /{fn_name}
-            let final_path = match (args.prefix, args.path) {
+            let final_path = match (&args.prefix, &args.path) {
                 (Some(prefix), Some(path)) => {
                     // Both prefix and path: concatenate them
                     format!("{}{}", prefix.value(), path.value())
@@ -79,13 +104,120 @@ pub fn component(attr: TokenStream, item: TokenStream) -> TokenStream {
 
             let method = args
                 .method
+                .as_ref()
                 .map(|m| m.value())
                 .unwrap_or_else(|| "GET".to_string());
-            (final_path, method)
+            (final_path, method, args)
         } else {
-            (format!("/{}", fn_name_str), "GET".to_string())
+            (
+                format!("/{}", fn_name_str),
+                "GET".to_string(),
+                ComponentArgs::default(),
+            )
+        }
+    };
+    let ComponentArgs {
+        persist,
+        root,
+        swap,
+        lazy,
+        placeholder,
+        poll,
+        backoff,
+        ..
+    } = args;
+
+    // Root element id and default swap, exposed on the route type
+    let root_id = match &root {
+        Some(lit) if lit.value().is_empty() || lit.value().contains(char::is_whitespace) => {
+            return syn::Error::new(lit.span(), "root must be an element id like \"todo-list\"")
+                .to_compile_error()
+                .into();
+        }
+        Some(lit) => {
+            let id = lit.value();
+            let id = id.strip_prefix('#').unwrap_or(&id).to_string();
+            quote! { Some(#id) }
+        }
+        None => quote! { None },
+    };
+    let default_swap = match (&swap, &root) {
+        (Some(lit), _) => match swap_variant(&lit.value()) {
+            Some(variant) => quote! { Some(::htmoxide::Swap::#variant) },
+            None => {
+                return syn::Error::new(
+                    lit.span(),
+                    "swap must be one of \"innerHTML\", \"outerHTML\", \"beforebegin\", \"afterbegin\", \"beforeend\", \"afterend\", \"delete\" or \"none\"",
+                )
+                .to_compile_error()
+                .into();
+            }
+        },
+        (None, Some(_)) => quote! { Some(::htmoxide::Swap::OuterHtml) },
+        (None, None) => quote! { None },
+    };
+    // Placeholder for lazy loading, see `htmoxide::lazy`
+    let placeholder = match placeholder {
+        Some(path) => quote! { Some(#path) },
+        None => quote! { None },
+    };
+    // Polling schedule, see `htmoxide::poll`
+    let poll = match (&poll, &backoff) {
+        (None, Some(lit)) => {
+            return syn::Error::new(lit.span(), "backoff requires poll = \"...\"")
+                .to_compile_error()
+                .into();
+        }
+        (None, None) => quote! { None },
+        (Some(poll), backoff) => {
+            let millis = |lit: &LitStr| {
+                duration_millis(&lit.value()).ok_or_else(|| {
+                    syn::Error::new(
+                        lit.span(),
+                        "expected a duration like \"500ms\", \"2s\" or \"1m\"",
+                    )
+                })
+            };
+            let interval = match millis(poll) {
+                Ok(ms) => ms,
+                Err(e) => return e.to_compile_error().into(),
+            };
+            let backoff = match backoff.as_ref().map(millis).transpose() {
+                Ok(Some(ms)) => quote! { .backoff(::std::time::Duration::from_millis(#ms)) },
+                Ok(None) => quote! {},
+                Err(e) => return e.to_compile_error().into(),
+            };
+            quote! {
+                Some(::htmoxide::Poll::every(::std::time::Duration::from_millis(#interval))#backoff)
+            }
         }
     };
+    // Per-component persistence override (falls back to the app setting)
+    let persist_override = match persist {
+        None => quote! { None },
+        Some(lit) => match lit.value().as_str() {
+            "none" => quote! { Some(::htmoxide::Persist::None) },
+            "cookie" => quote! { Some(::htmoxide::Persist::Cookie) },
+            "session" => quote! { Some(::htmoxide::Persist::Session) },
+            _ => {
+                return syn::Error::new(
+                    lit.span(),
+                    "persist must be one of \"none\", \"cookie\" or \"session\"",
+                )
+                .to_compile_error()
+                .into();
+            }
+        },
+    };
+
+    let component_consts = quote! {
+        const ROOT: Option<&'static str> = #root_id;
+        const SWAP: Option<::htmoxide::Swap> = #default_swap;
+        const LAZY: bool = #lazy;
+        const PLACEHOLDER: Option<fn() -> ::htmoxide::maud::Markup> = #placeholder;
+        const POLL: Option<::htmoxide::Poll> = #poll;
+        const PERSIST: Option<::htmoxide::Persist> = #persist_override;
+    };
 
     let vis = &input_fn.vis;
     let sig = &input_fn.sig;
@@ -153,6 +285,17 @@ pub fn component(attr: TokenStream, item: TokenStream) -> TokenStream {
         syn::Ident::new(&pascal_case, fn_name.span())
     };
 
+    let extractor_types: Vec<&syn::Type> =
+        extractors.iter().map(|(_, _, ty)| ty.as_ref()).collect();
+    let route_type = route_type(
+        vis,
+        &marker_type_name,
+        fn_name,
+        &route_path,
+        &extractor_types,
+        component_consts,
+    );
+
     // Build component function call with all parameters in ORIGINAL order
     let total_params = sig.inputs.len();
     let mut call_args = Vec::with_capacity(total_params);
@@ -186,12 +329,8 @@ pub fn component(attr: TokenStream, item: TokenStream) -> TokenStream {
                 // Extract from request parts (does not consume body)
                 let #extractor_name = match <#ty as ::axum::extract::FromRequestParts<()>>::from_request_parts(&mut parts, &()).await {
                     Ok(v) => v,
-                    Err(e) => {
-                        return ::axum::response::IntoResponse::into_response((
-                            ::axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                            format!("Failed to extract parameter {}: {:?}", stringify!(#ty), e),
-                        ));
-                    }
+                    // The extractor's own rejection, e.g. `403` for `Signed<T>`
+                    Err(e) => return ::axum::response::IntoResponse::into_response(e),
                 };
             }
         }).collect()
@@ -212,12 +351,7 @@ pub fn component(attr: TokenStream, item: TokenStream) -> TokenStream {
                 let req = ::axum::http::Request::from_parts(parts, body);
                 let #extractor_name = match <#ty as ::axum::extract::FromRequest<()>>::from_request(req, &()).await {
                     Ok(v) => v,
-                    Err(e) => {
-                        return ::axum::response::IntoResponse::into_response((
-                            ::axum::http::StatusCode::BAD_REQUEST,
-                            format!("Failed to extract body parameter {}: {:?}", stringify!(#ty), e),
-                        ));
-                    }
+                    Err(e) => return ::axum::response::IntoResponse::into_response(e),
                 };
             }
         } else {
@@ -225,12 +359,8 @@ pub fn component(attr: TokenStream, item: TokenStream) -> TokenStream {
                 // Regular extractor: use FromRequestParts
                 let #extractor_name = match <#ty as ::axum::extract::FromRequestParts<()>>::from_request_parts(&mut parts, &()).await {
                     Ok(v) => v,
-                    Err(e) => {
-                        return ::axum::response::IntoResponse::into_response((
-                            ::axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                            format!("Failed to extract parameter {}: {:?}", stringify!(#ty), e),
-                        ));
-                    }
+                    // The extractor's own rejection, e.g. `403` for `Signed<T>`
+                    Err(e) => return ::axum::response::IntoResponse::into_response(e),
                 };
             }
         }
@@ -262,104 +392,20 @@ pub fn component(attr: TokenStream, item: TokenStream) -> TokenStream {
                 let (mut parts, body) = req.into_parts();
 
                 // POSITION 0: Extract ViewState
-                // Auto-hydrate from query params (+ cookies if persist-state feature enabled)
-                let query_string = parts.uri.query().unwrap_or("").to_string();
-
-                // Extract state from query parameters
-                let mut state = match ::htmoxide::StateExtractor::<#state_type>::from_request_parts(
-                    &mut parts,
-                    &(),
-                ).await {
-                    Ok(extractor) => extractor.0,
-                    Err(_) => #state_type::default(),
-                };
+                // Auto-hydrate from query params (+ persisted state if enabled)
+                let state = ::htmoxide::hydrate::<#state_type>(&mut parts, #persist_override).await;
 
-                // Cookie hydration when persist-state feature is enabled
-                #[cfg(feature = "persist-state")]
-                {
-                    // Extract cookies for state persistence
-                    if let Ok(cookies) = ::htmoxide::tower_cookies::Cookies::from_request_parts(&mut parts, &()).await {
-                        // Merge cookie values into state (query params take priority)
-                        if let (Ok(default_json), Ok(mut state_json)) = (
-                            ::htmoxide::serde_json::to_value(&#state_type::default()),
-                            ::htmoxide::serde_json::to_value(&state)
-                        ) {
-                            if let (Some(default_obj), Some(state_obj)) = (
-                                default_json.as_object(),
-                                state_json.as_object_mut()
-                            ) {
-                                for (key, default_value) in default_obj {
-                                    if let Some(current_value) = state_obj.get(key) {
-                                        if current_value == default_value {
-                                            if let Some(cookie) = cookies.get(key) {
-                                                let cookie_value = cookie.value();
-                                                let parsed_value = if let Ok(num) = cookie_value.parse::<i64>() {
-                                                    Some(::htmoxide::serde_json::Value::Number(num.into()))
-                                                } else if let Ok(num) = cookie_value.parse::<f64>() {
-                                                    ::htmoxide::serde_json::Number::from_f64(num)
-                                                        .map(::htmoxide::serde_json::Value::Number)
-                                                } else if let Ok(b) = cookie_value.parse::<bool>() {
-                                                    Some(::htmoxide::serde_json::Value::Bool(b))
-                                                } else if !cookie_value.is_empty() {
-                                                    Some(::htmoxide::serde_json::Value::String(cookie_value.to_string()))
-                                                } else {
-                                                    None
-                                                };
-                                                if let Some(val) = parsed_value {
-                                                    state_obj.insert(key.clone(), val);
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                                if let Ok(new_state) = ::htmoxide::serde_json::from_value(state_json) {
-                                    state = new_state;
-                                }
-                            }
-                        }
-
-                        // Save current state to cookies for persistence
-                        if let Ok(state_json) = ::htmoxide::serde_json::to_value(&state) {
-                            if let ::htmoxide::serde_json::Value::Object(ref obj) = state_json {
-                                for (key, value) in obj {
-                                    let cookie_value = if let Some(value_str) = value.as_str() {
-                                        Some(value_str.to_string())
-                                    } else if let Some(value_num) = value.as_i64() {
-                                        Some(value_num.to_string())
-                                    } else if let Some(value_num) = value.as_f64() {
-                                        Some(value_num.to_string())
-                                    } else if let Some(value_bool) = value.as_bool() {
-                                        Some(value_bool.to_string())
-                                    } else {
-                                        None
-                                    };
-                                    if let Some(val) = cookie_value {
-                                        if val.is_empty() {
-                                            cookies.remove(::htmoxide::tower_cookies::Cookie::from(key.to_string()));
-                                        } else {
-                                            let mut cookie = ::htmoxide::tower_cookies::Cookie::new(key.to_string(), val);
-                                            cookie.set_path("/");
-                                            cookies.add(cookie);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+                // Outdated params were upgraded: redirect plain page loads to the canonical URL
+                let migration = parts.extensions.get::<::htmoxide::StateMigration>().cloned();
+                if let Some(redirect) = migration.as_ref().and_then(|m| m.redirect(&parts)) {
+                    return redirect;
                 }
 
                 // POSITION 1: Extract UrlBuilder
-                let main_page_path = parts.headers
-                    .get("HX-Current-URL")
-                    .and_then(|v| v.to_str().ok())
-                    .and_then(|url| url.split('?').next())
-                    .map(|path| path.to_string());
-
-                let url_builder = if let Some(page_path) = main_page_path {
-                    ::htmoxide::UrlBuilder::new(#route_path, &query_string).with_main_page(page_path)
-                } else {
-                    ::htmoxide::UrlBuilder::new(#route_path, &query_string)
-                };
+                let url_builder = ::htmoxide::UrlBuilder::for_request(#route_path, &parts)
+                    .with_persist(#persist_override)
+                    .with_state_schema::<#state_type>();
+                let poller = ::htmoxide::poll::Poller::new(&parts, &url_builder);
 
                 // POSITIONS 2+: Extract all additional Axum extractors
                 // All but last use FromRequestParts, last can use FromRequest (Form, Json)
@@ -370,20 +416,29 @@ pub fn component(attr: TokenStream, item: TokenStream) -> TokenStream {
 
                 // Call the component function with all parameters
                 #call_component
-                result.into_response()
-            })
-        }
+                let response = result.into_response();
 
-        // Zero-sized marker type for this component (for type-safe URL building)
-        #vis struct #marker_type_name;
+                // Make the root poll, from `poll = "..."` or `Html::poll_every`
+                let response = poller.apply::<#marker_type_name>(response).await;
 
-        // Implement ComponentName trait for type-safe component references
-        impl ::htmoxide::ComponentName for #marker_type_name {
-            fn name() -> &'static str {
-                stringify!(#fn_name)
-            }
+                // Development builds check the rendered root against `root = "..."`
+                let response = if cfg!(debug_assertions) {
+                    ::htmoxide::component::check_root::<#marker_type_name>(response).await
+                } else {
+                    response
+                };
+
+                // Replace outdated page URLs in the address bar
+                match migration {
+                    Some(migration) => migration.apply(response),
+                    None => response,
+                }
+            })
         }
 
+        // Route type for this component (for type-safe URL building)
+        #route_type
+
         // Register component in global registry
         ::htmoxide::inventory::submit! {
             ::htmoxide::ComponentInfo::new(
@@ -391,6 +446,8 @@ pub fn component(attr: TokenStream, item: TokenStream) -> TokenStream {
                 #route_path,
                 #handler_name,
                 #http_method,
+                ::htmoxide::state::state_keys::<#state_type>,
+                ::htmoxide::state::state_defaults::<#state_type>,
             )
         }
     };
@@ -398,11 +455,400 @@ pub fn component(attr: TokenStream, item: TokenStream) -> TokenStream {
     output.into()
 }
 
-/// Parse component arguments: prefix = "/api", method = "POST", path = "/{id}/action"
+/// Attribute macro for pages
+///
+/// Usage:
+/// - `#[page("/")]` - register an axum handler as a `GET` page route
+///
+/// Pages are registered with components, so `htmoxide::app()` mounts them and
+/// `UrlBuilder::for_page` resolves them through a generated route type
+/// (`index_page` -> `IndexPage`, with fields for path parameters like
+/// components have). Unlike components, pages take any axum extractors.
+#[proc_macro_attribute]
+pub fn page(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let route_path = parse_macro_input!(attr as LitStr).value();
+    let input_fn = parse_macro_input!(item as ItemFn);
+    let fn_name = &input_fn.sig.ident;
+    let vis = &input_fn.vis;
+
+    if input_fn.sig.asyncness.is_none() {
+        return syn::Error::new_spanned(&input_fn.sig, "Page handlers must be async")
+            .to_compile_error()
+            .into();
+    }
+
+    let handler_name = syn::Ident::new(&format!("__htmoxide_page_{}", fn_name), fn_name.span());
+    let type_name = syn::Ident::new(&to_pascal_case(&fn_name.to_string()), fn_name.span());
+
+    let extractor_types: Vec<&syn::Type> = input_fn
+        .sig
+        .inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            syn::FnArg::Typed(pat_type) => Some(pat_type.ty.as_ref()),
+            syn::FnArg::Receiver(_) => None,
+        })
+        .collect();
+    let route_type = route_type(
+        vis,
+        &type_name,
+        fn_name,
+        &route_path,
+        &extractor_types,
+        quote! {},
+    );
+
+    let output = quote! {
+        #input_fn
+
+        // Generate handler wrapper calling the page as an axum handler
+        #[doc(hidden)]
+        #vis fn #handler_name(
+            req: ::axum::http::Request<::axum::body::Body>,
+        ) -> ::std::pin::Pin<Box<dyn ::std::future::Future<Output = ::axum::response::Response> + Send>> {
+            Box::pin(::axum::handler::Handler::<_, ()>::call(#fn_name, req, ()))
+        }
+
+        // Route type for this page (for type-safe URL building)
+        #route_type
+
+        impl ::htmoxide::PageName for #type_name {}
+
+        // Register page in global registry
+        ::htmoxide::inventory::submit! {
+            ::htmoxide::ComponentInfo::page(stringify!(#fn_name), #route_path, #handler_name)
+        }
+    };
+
+    output.into()
+}
+
+/// Attribute macro for versioned view state types
+///
+/// Usage (place it above `#[derive]`):
+/// - `#[view_state]` - register the type (version 1)
+/// - `#[view_state(version = 2, migrate = migrate_fn)]` - upgrade older params with
+///   `fn migrate_fn(from: u32, params: &mut HashMap<String, String>)`
+/// - `#[view_state(version = 2, version_param = "tv")]` - carry the version in `tv`
+///   instead of `_v` (needed when a page combines several versioned states)
+/// - `#[view_state(compact)]` - pack the state into a single
+///   `<name>=<base64url(deflate(json))>` param named after the state in
+///   snake_case (`TodoState` -> `todo_state`) once the query exceeds 1024
+///   bytes; tune with `compact_param = "ds"` and `compact_threshold = 512`
+/// - `#[view_state(alias = "old_name")]` on a field - accept the field's old name
+#[proc_macro_attribute]
+pub fn view_state(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as ViewStateArgs);
+    let mut input = parse_macro_input!(item as syn::ItemStruct);
+    let name = &input.ident;
+
+    let syn::Fields::Named(fields) = &mut input.fields else {
+        return syn::Error::new_spanned(&input, "view_state requires a struct with named fields")
+            .to_compile_error()
+            .into();
+    };
+
+    // Aliases point at the field's position among the fields serde reads;
+    // the names themselves come from serde at runtime, so `rename_all` applies
+    let mut aliases = Vec::new();
+    let mut index = 0usize;
+    for field in fields.named.iter_mut() {
+        let field_index = index;
+        if !serde_skipped(&field.attrs) {
+            index += 1;
+        }
+
+        // Collect and strip our field attributes: #[view_state(alias = "...")]
+        let mut kept = Vec::with_capacity(field.attrs.len());
+        for attr in field.attrs.drain(..) {
+            if !attr.path().is_ident("view_state") {
+                kept.push(attr);
+                continue;
+            }
+            let parsed = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("alias") {
+                    let alias: LitStr = meta.value()?.parse()?;
+                    aliases.push((alias.value(), field_index));
+                    Ok(())
+                } else {
+                    Err(meta.error("Unknown view_state field attribute"))
+                }
+            });
+            if let Err(e) = parsed {
+                return e.to_compile_error().into();
+            }
+        }
+        field.attrs = kept;
+    }
+
+    let version = args.version;
+    let version_param = args.version_param.unwrap_or_else(|| "_v".to_string());
+    let alias_pairs = aliases.iter().map(|(old, index)| quote! { (#old, #index) });
+    let migrate = match args.migrate {
+        Some(path) => quote! { Some(#path) },
+        None => quote! { None },
+    };
+
+    let compact = if args.compact {
+        // Each state gets its own param, so several can be packed in one query
+        let param = args
+            .compact_param
+            .unwrap_or_else(|| to_snake_case(&name.to_string()));
+        let threshold = args.compact_threshold.unwrap_or(1024);
+        quote! { .compact(#param, #threshold) }
+    } else {
+        quote! {}
+    };
+
+    let output = quote! {
+        #input
+
+        // Register view state schema in global registry
+        ::htmoxide::inventory::submit! {
+            ::htmoxide::ViewStateInfo::new::<#name>(
+                stringify!(#name),
+                #version,
+                #version_param,
+                &[#(#alias_pairs),*],
+                || ::htmoxide::introspect::field_names::<#name>().unwrap_or(&[]),
+                #migrate,
+            )
+            #compact
+        }
+    };
+
+    output.into()
+}
+
+/// Route type for a component or page with its `ComponentName` impl: a unit
+/// marker, or one field per path parameter typed after the `Path` extractor
+fn route_type(
+    vis: &syn::Visibility,
+    type_name: &syn::Ident,
+    fn_name: &syn::Ident,
+    route_path: &str,
+    extractor_types: &[&syn::Type],
+    consts: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let path_params = route_params(route_path);
+    let path_param_types = path_extractor_types(extractor_types, path_params.len());
+    let param_names: Vec<&String> = path_params.iter().map(|(name, _)| name).collect();
+    let param_idents: Vec<syn::Ident> = path_params
+        .iter()
+        .map(|(name, _)| syn::Ident::new(name, fn_name.span()))
+        .collect();
+    let field_types: Vec<proc_macro2::TokenStream> = path_params
+        .iter()
+        .enumerate()
+        .map(|(idx, (_, wildcard))| match &path_param_types {
+            Some(types) if !wildcard => {
+                let ty = &types[idx];
+                quote! { #ty }
+            }
+            _ => quote! { String },
+        })
+        .collect();
+
+    let route_struct = if param_idents.is_empty() {
+        quote! { #vis struct #type_name; }
+    } else {
+        quote! {
+            #vis struct #type_name {
+                #(pub #param_idents: #field_types),*
+            }
+        }
+    };
+
+    quote! {
+        #route_struct
+
+        // Implement ComponentName trait for type-safe route references
+        impl ::htmoxide::ComponentName for #type_name {
+            #consts
+
+            fn name() -> &'static str {
+                stringify!(#fn_name)
+            }
+
+            fn path_params(&self) -> Vec<(&'static str, String)> {
+                vec![#((#param_names, self.#param_idents.to_string())),*]
+            }
+        }
+    }
+}
+
+/// Parameter names in a route path: `/todos/{id}/{*rest}` -> `[("id", false), ("rest", true)]`
+fn route_params(path: &str) -> Vec<(String, bool)> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+        .map(|name| match name.strip_prefix('*') {
+            Some(rest) => (rest.to_string(), true),
+            None => (name.to_string(), false),
+        })
+        .collect()
+}
+
+/// Types of the path parameters taken by a `Path<T>` or `Path<(A, B)>` extractor,
+/// if there is one with a matching number of parameters
+fn path_extractor_types(extractor_types: &[&syn::Type], count: usize) -> Option<Vec<syn::Type>> {
+    extractor_types.iter().find_map(|ty| {
+        let syn::Type::Path(type_path) = ty else {
+            return None;
+        };
+        let segment = type_path.path.segments.last()?;
+        if segment.ident != "Path" {
+            return None;
+        }
+        let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+            return None;
+        };
+        let Some(syn::GenericArgument::Type(inner)) = args.args.first() else {
+            return None;
+        };
+        let types = match inner {
+            syn::Type::Tuple(tuple) => tuple.elems.iter().cloned().collect(),
+            // A struct deserializes by field name and its field types aren't
+            // visible here, so only take a single parameter of a std type
+            syn::Type::Path(_) if count == 1 && is_std_scalar(inner) => vec![inner.clone()],
+            _ => return None,
+        };
+        (types.len() == count).then_some(types)
+    })
+}
+
+fn is_std_scalar(ty: &syn::Type) -> bool {
+    matches!(
+        extract_type_name(ty).as_str(),
+        "i8" | "i16"
+            | "i32"
+            | "i64"
+            | "i128"
+            | "isize"
+            | "u8"
+            | "u16"
+            | "u32"
+            | "u64"
+            | "u128"
+            | "usize"
+            | "f32"
+            | "f64"
+            | "bool"
+            | "char"
+            | "String"
+    )
+}
+
+/// Whether serde never reads the field: `#[serde(skip)]` or
+/// `#[serde(skip_deserializing)]`
+fn serde_skipped(attrs: &[syn::Attribute]) -> bool {
+    let mut skipped = false;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                skipped = true;
+            } else if let Ok(value) = meta.value() {
+                // Skip the values of other serde attributes
+                let _: syn::Expr = value.parse()?;
+            }
+            Ok(())
+        });
+    }
+    skipped
+}
+
+/// Parse view_state arguments: version = 2, migrate = path::to::fn, version_param = "_v",
+/// compact, compact_param = "ds", compact_threshold = 1024
+struct ViewStateArgs {
+    version: u32,
+    migrate: Option<syn::Path>,
+    version_param: Option<String>,
+    compact: bool,
+    compact_param: Option<String>,
+    compact_threshold: Option<usize>,
+}
+
+impl Parse for ViewStateArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut version = 1;
+        let mut migrate = None;
+        let mut version_param = None;
+        let mut compact = false;
+        let mut compact_param = None;
+        let mut compact_threshold = None;
+
+        // Parse comma-separated key = value pairs
+        while !input.is_empty() {
+            let key: syn::Ident = input.parse()?;
+
+            // Bare flag
+            if key == "compact" {
+                compact = true;
+                if input.peek(Token![,]) {
+                    let _comma: Token![,] = input.parse()?;
+                }
+                continue;
+            }
+
+            let _eq: Token![=] = input.parse()?;
+
+            match key.to_string().as_str() {
+                "version" => {
+                    let lit: syn::LitInt = input.parse()?;
+                    version = lit.base10_parse()?;
+                    if version == 0 {
+                        return Err(syn::Error::new(lit.span(), "version starts at 1"));
+                    }
+                }
+                "migrate" => migrate = Some(input.parse()?),
+                "version_param" => {
+                    let lit: LitStr = input.parse()?;
+                    version_param = Some(lit.value());
+                }
+                "compact_param" => {
+                    let lit: LitStr = input.parse()?;
+                    compact = true;
+                    compact_param = Some(lit.value());
+                }
+                "compact_threshold" => {
+                    let lit: syn::LitInt = input.parse()?;
+                    compact = true;
+                    compact_threshold = Some(lit.base10_parse()?);
+                }
+                _ => return Err(syn::Error::new(key.span(), "Unknown view_state attribute")),
+            }
+
+            // Parse optional comma
+            if input.peek(Token![,]) {
+                let _comma: Token![,] = input.parse()?;
+            }
+        }
+
+        Ok(ViewStateArgs {
+            version,
+            migrate,
+            version_param,
+            compact,
+            compact_param,
+            compact_threshold,
+        })
+    }
+}
+
+/// Parse component arguments: prefix = "/api", method = "POST", path = "/{id}/action", persist = "cookie",
+/// root = "todo-list", swap = "outerHTML", lazy, placeholder = skeleton_fn, poll = "2s",
+/// backoff = "30s"
+#[derive(Default)]
 struct ComponentArgs {
     prefix: Option<LitStr>,
     method: Option<LitStr>,
     path: Option<LitStr>,
+    persist: Option<LitStr>,
+    root: Option<LitStr>,
+    swap: Option<LitStr>,
+    lazy: bool,
+    placeholder: Option<syn::Path>,
+    poll: Option<LitStr>,
+    backoff: Option<LitStr>,
 }
 
 impl Parse for ComponentArgs {
@@ -410,17 +856,45 @@ impl Parse for ComponentArgs {
         let mut prefix = None;
         let mut method = None;
         let mut path = None;
+        let mut persist = None;
+        let mut root = None;
+        let mut swap = None;
+        let mut lazy = false;
+        let mut placeholder = None;
+        let mut poll = None;
+        let mut backoff = None;
 
         // Parse comma-separated key = "value" pairs
         while !input.is_empty() {
             let key: syn::Ident = input.parse()?;
+
+            // Bare flag and the one path-valued argument
+            if key == "lazy" {
+                lazy = true;
+                if input.peek(Token![,]) {
+                    let _comma: Token![,] = input.parse()?;
+                }
+                continue;
+            }
             let _eq: Token![=] = input.parse()?;
+            if key == "placeholder" {
+                placeholder = Some(input.parse()?);
+                if input.peek(Token![,]) {
+                    let _comma: Token![,] = input.parse()?;
+                }
+                continue;
+            }
             let value: LitStr = input.parse()?;
 
             match key.to_string().as_str() {
                 "prefix" => prefix = Some(value),
                 "method" => method = Some(value),
                 "path" => path = Some(value),
+                "persist" => persist = Some(value),
+                "root" => root = Some(value),
+                "swap" => swap = Some(value),
+                "poll" => poll = Some(value),
+                "backoff" => backoff = Some(value),
                 _ => return Err(syn::Error::new(key.span(), "Unknown component attribute")),
             }
 
@@ -434,10 +908,63 @@ impl Parse for ComponentArgs {
             prefix,
             method,
             path,
+            persist,
+            root,
+            swap,
+            lazy,
+            placeholder,
+            poll,
+            backoff,
         })
     }
 }
 
+/// `Swap` variant for an `hx-swap` value
+fn swap_variant(value: &str) -> Option<syn::Ident> {
+    let variant = match value {
+        "innerHTML" => "InnerHtml",
+        "outerHTML" => "OuterHtml",
+        "beforebegin" => "BeforeBegin",
+        "afterbegin" => "AfterBegin",
+        "beforeend" => "BeforeEnd",
+        "afterend" => "AfterEnd",
+        "delete" => "Delete",
+        "none" => "None",
+        _ => return None,
+    };
+    Some(syn::Ident::new(variant, proc_macro2::Span::call_site()))
+}
+
+/// Milliseconds of a duration like "500ms", "2s" or "1m"
+fn duration_millis(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (number, unit) = value.split_at(value.find(|c: char| !c.is_ascii_digit())?);
+    let number: u64 = number.parse().ok()?;
+    let millis = match unit.trim() {
+        "ms" => number,
+        "s" => number.checked_mul(1000)?,
+        "m" => number.checked_mul(60_000)?,
+        _ => return None,
+    };
+    (millis > 0).then_some(millis)
+}
+
+/// Convert PascalCase to snake_case
+fn to_snake_case(s: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in s.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
 /// Convert snake_case to PascalCase
 fn to_pascal_case(s: &str) -> String {
     s.split('_')
@@ -33,13 +33,19 @@ use tower_http::services::ServeDir;
 ///     .page("/", index);
 /// ```
 pub fn app() -> Router {
+    crate::compact::check_params();
     let mut router = Router::new();
 
     // Register all components from the global registry
     for component in inventory::iter::<crate::ComponentInfo> {
+        let kind = if component.is_page {
+            "page"
+        } else {
+            "component"
+        };
         println!(
-            "Registering component: {} at {} ({})",
-            component.name, component.path, component.method
+            "Registering {}: {} at {} ({})",
+            kind, component.name, component.path, component.method
         );
         let handler = component.handler;
 
@@ -129,7 +135,9 @@ pub trait HtmxRouterExt<S>: Sized {
     /// and enables bookmarking/sharing with current state.
     ///
     /// By default, sensitive cookies are excluded (token, session, auth, etc.).
-    /// Use `with_state_urls_custom()` to customize the denylist.
+    /// Use `with_state_urls_custom()` to customize the denylist, or to render
+    /// directly and sync the URL client-side (`StateUrlsMode::ClientSync`)
+    /// instead of redirecting.
     ///
     /// # Example
     /// User visits `/simple` with `count=3` in cookies
@@ -155,14 +163,113 @@ pub trait HtmxRouterExt<S>: Sized {
     /// use htmoxide::StateUrlsConfig;
     ///
     /// let config = StateUrlsConfig::new()
-    ///     .deny(["my_secret", "internal_state"]);
+    ///     .deny(["my_secret", "internal_state"])
+    ///     .deny_glob("_ga*")
+    ///     .deny_suffix("_token");
     ///
     /// let app = app()
     ///     .route("/", index_page)
     ///     .htmx()
     ///     .with_state_urls_custom(config);
     /// ```
+    ///
+    /// # Example with allowlist mode
+    /// Only keys of the view states mounted on the requested page are promoted:
+    /// ```ignore
+    /// let config = StateUrlsConfig::default()
+    ///     .mount::<TodoList>("/")
+    ///     .mount::<ProjectTable>("/projects/{id}");
+    /// ```
     fn with_state_urls_custom(self, config: crate::StateUrlsConfig) -> Self;
+
+    /// Sets how component view state is persisted between requests.
+    ///
+    /// Accepts a `Persist` mode, or a `PersistConfig` to also choose the
+    /// storage backends. Components can override the mode with
+    /// `#[component(persist = "...")]`.
+    /// Without this call, state is only read from the URL.
+    ///
+    /// # Note
+    /// - Must be called AFTER adding all routes, like `.htmx()`
+    /// - Cookie and session persistence require the layers added by `.htmx()`
+    ///
+    /// ```ignore
+    /// use htmoxide::Persist;
+    ///
+    /// let app = app()
+    ///     .route("/", index_page)
+    ///     .htmx()
+    ///     .persist_state(Persist::Cookie);
+    /// ```
+    ///
+    /// # Example with a custom store
+    /// ```ignore
+    /// use htmoxide::{Persist, PersistConfig, store::SessionStore};
+    ///
+    /// let app = app()
+    ///     .route("/", index_page)
+    ///     .htmx()
+    ///     .persist_state(
+    ///         PersistConfig::new(Persist::Session).session_store(SessionStore::file("sessions")),
+    ///     );
+    /// ```
+    fn persist_state(self, config: impl Into<crate::PersistConfig>) -> Self;
+
+    /// Mounts the app under a path prefix.
+    ///
+    /// A plain prefix serves all routes under it; use `BasePath::proxied` when
+    /// a reverse proxy strips the prefix, optionally reading it from
+    /// `X-Forwarded-Prefix`. Generated URLs, state URL redirects and
+    /// `BasePrefix::url()` carry the prefix.
+    ///
+    /// # Note
+    /// - Call this LAST, so every other layer sees the resolved prefix
+    ///
+    /// ```ignore
+    /// use htmoxide::BasePath;
+    ///
+    /// let app = app()
+    ///     .route("/", index_page)
+    ///     .htmx()
+    ///     .base_path("/tools/todo");
+    ///
+    /// let app = app()
+    ///     .htmx()
+    ///     .base_path(BasePath::proxied("/tools/todo").forwarded(true));
+    /// ```
+    fn base_path(self, base: impl Into<crate::BasePath>) -> Self;
+
+    /// Sets the scheme and host `UrlBuilder::absolute()` URLs point at.
+    ///
+    /// Accepts a fixed origin like `"https://example.com"`, or
+    /// `PublicOrigin::Forwarded` to trust `X-Forwarded-Proto`/`X-Forwarded-Host`.
+    /// Without it absolute URLs stay relative, as the `Host` header can't be
+    /// trusted.
+    ///
+    /// ```ignore
+    /// let app = app()
+    ///     .route("/", index_page)
+    ///     .htmx()
+    ///     .public_origin("https://todo.example.com");
+    /// ```
+    fn public_origin(self, origin: impl Into<crate::PublicOrigin>) -> Self;
+
+    /// Sets the key the `Signed<T>` extractor verifies links with.
+    ///
+    /// Use the same key for `UrlBuilder::signed()`. Links stop verifying
+    /// when the key changes, so load it from configuration rather than
+    /// generating it at startup in production.
+    ///
+    /// ```ignore
+    /// use htmoxide::tower_cookies::Key;
+    ///
+    /// let key = Key::from(&secret_bytes);
+    /// let app = app()
+    ///     .route("/", index_page)
+    ///     .htmx()
+    ///     .signing_key(key);
+    /// ```
+    fn signing_key(self, key: tower_cookies::Key) -> Self;
 }
 
 impl<S> HtmxRouterExt<S> for Router<S>
@@ -184,4 +291,28 @@ where
             crate::state_urls_middleware::state_urls_middleware_impl(config, cookies, request, next)
         }))
     }
+
+    fn persist_state(self, config: impl Into<crate::PersistConfig>) -> Self {
+        self.layer(Extension(config.into()))
+    }
+
+    fn base_path(self, base: impl Into<crate::BasePath>) -> Self {
+        let base = base.into();
+        let router = if base.is_nested() && !base.prefix().is_empty() {
+            Router::new().nest(base.prefix(), self)
+        } else {
+            self
+        };
+        router.layer(axum::middleware::from_fn(move |request, next| {
+            crate::base_path::base_path_middleware(base.clone(), request, next)
+        }))
+    }
+
+    fn public_origin(self, origin: impl Into<crate::PublicOrigin>) -> Self {
+        self.layer(Extension(origin.into()))
+    }
+
+    fn signing_key(self, key: tower_cookies::Key) -> Self {
+        self.layer(Extension(crate::SigningKey(key)))
+    }
 }
@@ -0,0 +1,272 @@
+//! Serving the app under a path prefix
+//!
+//! Routes registered by `#[component]` and `#[page]` are absolute paths like
+//! `/todos/{id}/toggle`. When the app is mounted under a prefix, either by
+//! htmoxide itself or by a reverse proxy that strips it, every URL handed to
+//! the browser must carry the prefix again:
+//!
+//! ```rust,ignore
+//! // Serve the app at /tools/todo/...
+//! let app = htmoxide::app().htmx().base_path("/tools/todo");
+//!
+//! // A proxy strips the prefix and reports it in `X-Forwarded-Prefix`
+//! let app = htmoxide::app().htmx().base_path(BasePath::proxied("").forwarded(true));
+//! ```
+//!
+//! `UrlBuilder`, the state URLs middleware and view state redirects add the
+//! prefix automatically. For asset URLs and other links, extract [`BasePrefix`]:
+//!
+//! ```rust,ignore
+//! async fn index_page(base: BasePrefix) -> Page {
+//!     html! { link rel="stylesheet" href=(base.url("/static/app.css")); }.into()
+//! }
+//! ```
+//!
+//! # Public origin
+//!
+//! `UrlBuilder::absolute()` needs the scheme and host the app is reached at.
+//! Configure it with `HtmxRouterExt::public_origin`. The `Host` header is
+//! client-controlled, so it is never trusted on its own: without a configured
+//! origin, absolute URLs stay relative.
+//!
+//! ```rust,ignore
+//! let app = htmoxide::app().htmx().public_origin("https://todo.example.com");
+//!
+//! // Behind a proxy setting `X-Forwarded-Proto` and `X-Forwarded-Host`
+//! let app = htmoxide::app().htmx().public_origin(PublicOrigin::Forwarded);
+//! ```
+
+use axum::{
+    extract::{FromRequestParts, Request},
+    http::{Extensions, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+use std::convert::Infallible;
+
+/// Header a reverse proxy uses to report the prefix it stripped
+pub const FORWARDED_PREFIX_HEADER: &str = "X-Forwarded-Prefix";
+
+/// Where the app is mounted, configured with `HtmxRouterExt::base_path`
+#[derive(Clone, Debug, Default)]
+pub struct BasePath {
+    prefix: String,
+    nested: bool,
+    forwarded: bool,
+}
+
+impl BasePath {
+    /// Serve the app's routes under `prefix`
+    pub fn new(prefix: impl AsRef<str>) -> Self {
+        Self {
+            prefix: normalize(prefix.as_ref()),
+            nested: true,
+            forwarded: false,
+        }
+    }
+
+    /// A proxy strips `prefix` before requests reach the app; routes stay
+    /// at the root and only generated URLs carry the prefix
+    pub fn proxied(prefix: impl AsRef<str>) -> Self {
+        Self {
+            prefix: normalize(prefix.as_ref()),
+            nested: false,
+            forwarded: false,
+        }
+    }
+
+    /// Use the prefix from `X-Forwarded-Prefix` when a request carries one
+    ///
+    /// Only enable this behind a proxy that sets or strips the header.
+    pub fn forwarded(mut self, forwarded: bool) -> Self {
+        self.forwarded = forwarded;
+        self
+    }
+
+    /// The configured prefix (`""` for the root)
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Whether the router itself serves the routes under the prefix
+    pub fn is_nested(&self) -> bool {
+        self.nested
+    }
+
+    /// The prefix for a request, honouring `X-Forwarded-Prefix` if enabled
+    pub fn resolve(&self, parts: &Parts) -> BasePrefix {
+        let forwarded = self
+            .forwarded
+            .then(|| parts.headers.get(FORWARDED_PREFIX_HEADER))
+            .flatten()
+            .and_then(|v| v.to_str().ok())
+            .filter(|prefix| is_safe_prefix(prefix));
+        match forwarded {
+            Some(prefix) => BasePrefix(normalize(prefix)),
+            None => BasePrefix(self.prefix.clone()),
+        }
+    }
+}
+
+impl From<&str> for BasePath {
+    fn from(prefix: &str) -> Self {
+        Self::new(prefix)
+    }
+}
+
+impl From<String> for BasePath {
+    fn from(prefix: String) -> Self {
+        Self::new(prefix)
+    }
+}
+
+/// The path prefix URLs for the current request need, `""` at the root
+///
+/// Available as an extractor and in request extensions once
+/// `base_path()` is configured.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BasePrefix(pub String);
+
+impl BasePrefix {
+    /// Get the request's prefix from extensions (`""` without `base_path()`)
+    pub fn from_extensions(extensions: &Extensions) -> &str {
+        extensions
+            .get::<BasePrefix>()
+            .map(|prefix| prefix.0.as_str())
+            .unwrap_or("")
+    }
+
+    /// Prefix an app path: `/static/app.css` -> `/tools/todo/static/app.css`
+    pub fn url(&self, path: &str) -> String {
+        join(&self.0, path)
+    }
+
+    /// Remove the prefix from a path seen by the browser, if present
+    pub fn strip<'a>(&self, path: &'a str) -> &'a str {
+        strip(&self.0, path)
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for BasePrefix {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(BasePrefix(
+            BasePrefix::from_extensions(&parts.extensions).to_string(),
+        ))
+    }
+}
+
+/// Scheme and host generated absolute URLs point at
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PublicOrigin {
+    /// A fixed origin such as `https://example.com`
+    Fixed(String),
+    /// Trust `X-Forwarded-Proto` and `X-Forwarded-Host`, falling back to `Host`
+    ///
+    /// Only use this behind a proxy that sets or strips these headers.
+    Forwarded,
+}
+
+impl PublicOrigin {
+    /// The origin for a request, if it can be determined
+    pub fn resolve(&self, parts: &Parts) -> Option<String> {
+        match self {
+            PublicOrigin::Fixed(origin) => Some(origin.clone()),
+            PublicOrigin::Forwarded => {
+                let header = |name: &str| {
+                    parts
+                        .headers
+                        .get(name)
+                        .and_then(|v| v.to_str().ok())
+                        // Proxies may append; the first entry is the client-facing one
+                        .and_then(|v| v.split(',').next())
+                        .map(str::trim)
+                };
+                let scheme = header("X-Forwarded-Proto").unwrap_or("http");
+                let host = header("X-Forwarded-Host").or_else(|| header("Host"))?;
+                origin_from(scheme, host)
+            }
+        }
+    }
+
+    /// The configured origin for a request, `None` without `public_origin()`
+    pub fn from_parts(parts: &Parts) -> Option<String> {
+        parts.extensions.get::<PublicOrigin>()?.resolve(parts)
+    }
+}
+
+impl From<&str> for PublicOrigin {
+    fn from(origin: &str) -> Self {
+        PublicOrigin::Fixed(origin.trim_end_matches('/').to_string())
+    }
+}
+
+impl From<String> for PublicOrigin {
+    fn from(origin: String) -> Self {
+        PublicOrigin::from(origin.as_str())
+    }
+}
+
+/// `scheme://host` from header values, rejecting anything that isn't a plain
+/// host name so the origin is safe in HTML attributes and headers
+fn origin_from(scheme: &str, host: &str) -> Option<String> {
+    let valid_scheme = matches!(scheme, "http" | "https");
+    let valid_host = !host.is_empty()
+        && host
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b':' | b'[' | b']'));
+    (valid_scheme && valid_host).then(|| format!("{}://{}", scheme, host))
+}
+
+/// Middleware resolving the prefix of each request into its extensions
+pub(crate) async fn base_path_middleware(base: BasePath, request: Request, next: Next) -> Response {
+    let (mut parts, body) = request.into_parts();
+    let prefix = base.resolve(&parts);
+    parts.extensions.insert(prefix);
+    next.run(Request::from_parts(parts, body)).await
+}
+
+/// Prefix an app path; the app root maps to the bare prefix, which is what
+/// a nested router serves
+pub(crate) fn join(prefix: &str, path: &str) -> String {
+    if prefix.is_empty() {
+        path.to_string()
+    } else if path == "/" || path.is_empty() {
+        prefix.to_string()
+    } else {
+        format!("{}{}", prefix, path)
+    }
+}
+
+/// Remove a prefix from a browser path, keeping paths outside it as they are
+pub(crate) fn strip<'a>(prefix: &str, path: &'a str) -> &'a str {
+    if prefix.is_empty() {
+        return path;
+    }
+    match path.strip_prefix(prefix) {
+        Some("") => "/",
+        Some(rest) if rest.starts_with('/') => rest,
+        _ => path,
+    }
+}
+
+/// `tools/todo/` -> `/tools/todo`, `/` -> ``
+fn normalize(prefix: &str) -> String {
+    let trimmed = prefix.trim_matches('/');
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", trimmed)
+    }
+}
+
+/// Header values end up in HTML attributes and `Location` headers, so only
+/// accept plain path characters
+fn is_safe_prefix(prefix: &str) -> bool {
+    prefix.starts_with('/')
+        && !prefix.starts_with("//")
+        && prefix
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'/' | b'-' | b'_' | b'.' | b'~'))
+}
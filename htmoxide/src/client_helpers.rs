@@ -1,5 +1,5 @@
+use crate::state::FIELDS_PARAM;
 use maud::{Markup, PreEscaped, html};
-use std::collections::HashMap;
 
 /// Returns a script tag that clears cookies for empty parameter values.
 ///
@@ -7,19 +7,26 @@ use std::collections::HashMap;
 /// which would cause old cookie values to persist. The script listens for htmx
 /// requests and clears cookies client-side when parameters are sent as empty strings.
 ///
+/// The server now clears persisted values itself for fields a request carries
+/// as empty or declares with [`state_fields`], which also works without
+/// JavaScript, for non-htmx submissions and for `HttpOnly` cookies.
+///
 /// Include this in your HTML head after loading htmx:
 ///
 /// ```rust
+/// # #![allow(deprecated)]
 /// use htmoxide::prelude::*;
 /// use htmoxide::cookie_cleaner_script;
 ///
+/// # let _ =
 /// html! {
 ///     head {
 ///         script src="https://unpkg.com/htmx.org@1.9.10" {}
 ///         (cookie_cleaner_script())
 ///     }
-/// }
+/// };
 /// ```
+#[deprecated(note = "the server clears omitted fields; declare them with `state_fields()`")]
 pub fn cookie_cleaner_script() -> Markup {
     html! {
         script {
@@ -41,33 +48,95 @@ pub fn cookie_cleaner_script() -> Markup {
     }
 }
 
+/// Renders a hidden input declaring which state fields a form submits.
+///
+/// Browsers leave unchecked checkboxes (and disabled inputs) out of the
+/// request entirely. Listing the fields lets the server tell "omitted" from
+/// "not part of this form" and reset omitted fields instead of restoring
+/// their persisted value.
+///
+/// # Example
+/// ```rust
+/// use htmoxide::prelude::*;
+/// use htmoxide::state_fields;
+///
+/// # let _ =
+/// html! {
+///     form hx-get="/todo_container" {
+///         input type="checkbox" name="show_completed" value="true";
+///         (state_fields(&["show_completed"]))
+///     }
+/// };
+/// ```
+pub fn state_fields(fields: &[&str]) -> Markup {
+    html! {
+        input type="hidden" name=(FIELDS_PARAM) value=(fields.join(","));
+    }
+}
+
+/// Returns an `hx-vals` JSON object declaring which state fields a request submits.
+///
+/// Same as [`state_fields`] for elements that are not forms.
+///
+/// # Example
+/// ```rust
+/// use htmoxide::prelude::*;
+/// use htmoxide::state_fields_vals;
+///
+/// # let _ =
+/// html! {
+///     input type="checkbox" name="show_completed" value="true"
+///         hx-get="/todo_container"
+///         hx-vals=(state_fields_vals(&["show_completed"]));
+/// };
+/// ```
+pub fn state_fields_vals(fields: &[&str]) -> String {
+    serde_json::json!({ FIELDS_PARAM: fields.join(",") }).to_string()
+}
+
 /// Renders hidden input fields to preserve URL parameters.
 ///
 /// Useful in forms that need to maintain other component state while updating one parameter.
 /// This prevents state loss when only part of the URL parameters are being updated.
+/// Pass `url.other_params::<MyState>()` to keep everything except the
+/// component's own state.
 ///
 /// # Arguments
-/// * `params` - HashMap of all current URL parameters
+/// * `params` - All current URL parameters, e.g. `url.all_params()` or a `HashMap`;
+///   repeated keys render one input per value
 /// * `exclude` - Slice of parameter names to exclude (typically the ones being actively edited)
 ///
 /// # Example
 /// ```rust
 /// use htmoxide::prelude::*;
 /// use htmoxide::preserve_params;
+/// # use std::collections::HashMap;
+/// # struct State { filter: String }
+/// # let state = State { filter: String::new() };
+/// # let all_params: HashMap<String, String> = HashMap::new();
 ///
 /// // In a component that edits "filter" but wants to preserve "sort", "count", etc.
+/// # let _ =
 /// html! {
 ///     form {
 ///         input type="text" name="filter" value=(state.filter);
 ///         (preserve_params(&all_params, &["filter"]))
 ///         button { "Submit" }
 ///     }
-/// }
+/// };
 /// ```
-pub fn preserve_params(params: &HashMap<String, String>, exclude: &[&str]) -> Markup {
+pub fn preserve_params<K, V>(params: impl IntoIterator<Item = (K, V)>, exclude: &[&str]) -> Markup
+where
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    // Sorted by key so the rendered form is stable, repeated values keep their order
+    let mut params: Vec<(K, V)> = params.into_iter().collect();
+    params.sort_by(|(a, _), (b, _)| a.as_ref().cmp(b.as_ref()));
     html! {
-        @for (key, value) in params {
-            @if !exclude.contains(&key.as_str()) && !value.is_empty() {
+        @for (key, value) in &params {
+            @let (key, value) = (key.as_ref(), value.as_ref());
+            @if !exclude.contains(&key) && key != FIELDS_PARAM && !value.is_empty() {
                 input type="hidden" name=(key) value=(value);
             }
         }
@@ -86,12 +155,14 @@ pub fn preserve_params(params: &HashMap<String, String>, exclude: &[&str]) -> Ma
 /// # Example
 /// ```rust
 /// use htmoxide::clear_input_handler;
+/// use htmoxide::prelude::*;
 ///
+/// # let _ =
 /// html! {
 ///     button onclick=(clear_input_handler("search-input", "keyup")) {
 ///         "Clear"
 ///     }
-/// }
+/// };
 /// ```
 pub fn clear_input_handler(input_id: &str, event: &str) -> String {
     format!(
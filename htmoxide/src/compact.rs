@@ -0,0 +1,245 @@
+//! Compact encoding for large view states
+//!
+//! Dashboards with many filters produce query strings long enough to hit
+//! proxy and browser limits. A view state can opt into packing its params
+//! into a single compressed param once the query grows past a threshold:
+//!
+//! ```rust,ignore
+//! // ?dashboard_state=<base64url(deflate(json))> once the query exceeds 1024 bytes
+//! #[view_state(compact)]
+//! #[derive(Deserialize, Serialize, Default)]
+//! pub struct DashboardState { /* ... */ }
+//!
+//! // Custom param and threshold
+//! #[view_state(compact_param = "ds", compact_threshold = 512)]
+//! ```
+//!
+//! The payload is a JSON object of the state's params (`{"status": "open",
+//! "tag": ["a", "b"]}`), so upgrades, the `_fields` manifest and persistence
+//! work on it as on readable params. `StateExtractor`, `UrlBuilder` and the
+//! state URLs middleware unpack it transparently; readable params next to it
+//! win, so `?dashboard_state=...&page=2` still works. Short queries stay
+//! readable.
+//!
+//! The param defaults to the state's name in snake case, so every compact
+//! state on a page packs into its own param. [`app`](crate::app) panics if
+//! two registered states claim the same param.
+
+use crate::query::QueryMap;
+use crate::view_state::ViewStateInfo;
+use axum::http::{Uri, request::Parts};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use flate2::{Compression, read::DeflateDecoder, write::DeflateEncoder};
+use std::borrow::Cow;
+use std::io::{Read, Write};
+
+/// Default query length above which states are packed
+pub const DEFAULT_COMPACT_THRESHOLD: usize = 1024;
+
+/// Upper bound on an unpacked payload, against decompression bombs
+const MAX_DECODED_LEN: u64 = 64 * 1024;
+
+/// How a view state registered with `#[view_state(compact)]` is packed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompactEncoding {
+    /// Query param holding the packed state
+    pub param: &'static str,
+    /// Query length in bytes above which the state is packed
+    pub threshold: usize,
+}
+
+/// Pack params into a compact value: base64url of deflated JSON
+pub fn encode(params: &QueryMap) -> String {
+    let mut object = serde_json::Map::new();
+    for key in params.keys() {
+        let values: Vec<&str> = params.get_all(key).collect();
+        let value = match values.as_slice() {
+            [single] => serde_json::Value::from(*single),
+            _ => serde_json::Value::from(values),
+        };
+        object.insert(key.to_string(), value);
+    }
+
+    let json = serde_json::Value::Object(object).to_string();
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    // Writing to a Vec can't fail
+    let _ = encoder.write_all(json.as_bytes());
+    URL_SAFE_NO_PAD.encode(encoder.finish().unwrap_or_default())
+}
+
+/// Unpack a compact value, `None` if it is malformed
+pub fn decode(value: &str) -> Option<QueryMap> {
+    let compressed = URL_SAFE_NO_PAD.decode(value.trim_end_matches('=')).ok()?;
+    let mut json = Vec::new();
+    DeflateDecoder::new(compressed.as_slice())
+        .take(MAX_DECODED_LEN)
+        .read_to_end(&mut json)
+        .ok()?;
+
+    let serde_json::Value::Object(object) = serde_json::from_slice(&json).ok()? else {
+        return None;
+    };
+    let mut params = QueryMap::new();
+    for (key, value) in object {
+        match value {
+            serde_json::Value::Array(values) => {
+                params.extend(values.iter().filter_map(scalar).map(|v| (key.clone(), v)))
+            }
+            value => params.extend(scalar(&value).map(|v| (key, v))),
+        }
+    }
+    Some(params)
+}
+
+fn scalar(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        serde_json::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn compact_states() -> impl Iterator<Item = (&'static ViewStateInfo, CompactEncoding)> {
+    inventory::iter::<ViewStateInfo>
+        .into_iter()
+        .filter_map(|info| info.compact.map(|compact| (info, compact)))
+}
+
+/// Panic if a compact param is claimed twice, or is a param of another
+/// registered state
+pub(crate) fn check_params() {
+    let mut conflicts = Vec::new();
+    for (info, compact) in compact_states() {
+        for other in inventory::iter::<ViewStateInfo> {
+            let param = compact.param;
+            let claimed = (other.fields)().contains(&param)
+                || other.version_param == param
+                // Report clashing compact states once per pair
+                || (info.name < other.name && other.compact.is_some_and(|c| c.param == param));
+            if claimed {
+                conflicts.push(format!("`{}` ({} and {})", param, info.name, other.name));
+            }
+        }
+    }
+    if !conflicts.is_empty() {
+        panic!(
+            "compact view state params must be unique: {}; set `compact_param`",
+            conflicts.join(", ")
+        );
+    }
+}
+
+/// Replace packed states with their readable params
+///
+/// Returns whether anything was unpacked. Values that don't decode are left
+/// alone, as they may be a plain param of another component.
+pub(crate) fn expand(params: &mut QueryMap) -> bool {
+    let mut expanded = false;
+    for (_, compact) in compact_states() {
+        let Some(packed) = params.get(compact.param).and_then(decode) else {
+            continue;
+        };
+        params.remove(compact.param);
+        expanded = true;
+        for key in packed.keys() {
+            if !params.contains_key(key) {
+                params.set_all(key, packed.get_all(key));
+            }
+        }
+    }
+    expanded
+}
+
+/// Readable form of a query string
+pub(crate) fn expand_query(query: &str) -> Cow<'_, str> {
+    let mut params = QueryMap::parse(query);
+    if expand(&mut params) {
+        Cow::Owned(params.to_string())
+    } else {
+        Cow::Borrowed(query)
+    }
+}
+
+/// Pack the params of compact states whose query grew past their threshold
+///
+/// The packed param takes the position of the state's first param, so
+/// canonical ordering is kept.
+pub(crate) fn pack(params: &mut QueryMap) {
+    for (info, compact) in compact_states() {
+        if params.to_string().len() <= compact.threshold {
+            continue;
+        }
+        let is_packed = |key: &str| key == info.version_param || (info.fields)().contains(&key);
+        let mut state = params.clone();
+        state.retain(|key, _| is_packed(key));
+        if state.is_empty() {
+            continue;
+        }
+
+        let mut packed = QueryMap::new();
+        let mut value = Some(encode(&state));
+        for (key, v) in params.iter() {
+            if !is_packed(key) {
+                packed.append(key, v);
+            } else if let Some(value) = value.take() {
+                packed.append(compact.param, value);
+            }
+        }
+        *params = packed;
+    }
+}
+
+/// Compact form of a query string, see [`pack`]
+pub(crate) fn pack_query(query: &str) -> String {
+    let mut params = QueryMap::parse(query);
+    pack(&mut params);
+    params.to_string()
+}
+
+/// Rewrite the request URI to readable params, so later extractors and the
+/// `UrlBuilder` see every field
+pub(crate) fn expand_request(parts: &mut Parts) {
+    let Some(query) = parts.uri.query() else {
+        return;
+    };
+    if let Cow::Owned(readable) = expand_query(query) {
+        let uri = if readable.is_empty() {
+            parts.uri.path().to_string()
+        } else {
+            format!("{}?{}", parts.uri.path(), readable)
+        };
+        if let Ok(uri) = uri.parse::<Uri>() {
+            parts.uri = uri;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_round_trips_single_and_repeated_values() {
+        let params = QueryMap::parse("status=open&tag=a&tag=b&note=a%20b%26c");
+        let decoded = decode(&encode(&params)).unwrap();
+
+        assert_eq!(decoded.get("status"), Some("open"));
+        assert_eq!(decoded.get_all("tag").collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(decoded.get("note"), Some("a b&c"));
+    }
+
+    #[test]
+    fn decode_rejects_malformed_values() {
+        assert!(decode("not-deflate").is_none());
+        assert!(decode(&URL_SAFE_NO_PAD.encode(b"[1]")).is_none());
+    }
+
+    #[test]
+    fn expand_keeps_values_that_do_not_decode() {
+        let mut params = QueryMap::parse("s=plain");
+        assert!(!expand(&mut params));
+        assert_eq!(params.to_string(), "s=plain");
+    }
+}
@@ -1,4 +1,13 @@
-use axum::{body::Body, http::Request, response::Response};
+use crate::hx::Swap;
+use crate::url_builder::ComponentName;
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+    response::Response,
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
 
@@ -12,6 +21,12 @@ pub struct ComponentInfo {
     pub path: &'static str,
     pub handler: ComponentHandler,
     pub method: &'static str,
+    /// Query keys of the component's view state
+    pub state_fields: fn() -> Vec<String>,
+    /// Default values of the view state's fields
+    pub state_defaults: fn() -> HashMap<String, String>,
+    /// Registered with `#[page]` rather than `#[component]`
+    pub is_page: bool,
 }
 
 impl ComponentInfo {
@@ -20,14 +35,39 @@ impl ComponentInfo {
         path: &'static str,
         handler: ComponentHandler,
         method: &'static str,
+        state_fields: fn() -> Vec<String>,
+        state_defaults: fn() -> HashMap<String, String>,
     ) -> Self {
         Self {
             name,
             path,
             handler,
             method,
+            state_fields,
+            state_defaults,
+            is_page: false,
         }
     }
+
+    /// Registry entry for a `#[page]` handler
+    pub const fn page(name: &'static str, path: &'static str, handler: ComponentHandler) -> Self {
+        Self {
+            name,
+            path,
+            handler,
+            method: "GET",
+            state_fields: Vec::new,
+            state_defaults: HashMap::new,
+            is_page: true,
+        }
+    }
+}
+
+/// Look up a registered component by name
+pub fn component_info(component_name: &str) -> Option<&'static ComponentInfo> {
+    inventory::iter::<ComponentInfo>
+        .into_iter()
+        .find(|component| component.name == component_name)
 }
 
 /// Trait for component registration
@@ -37,3 +77,81 @@ pub trait Component {
 
 // Global component registry using inventory
 inventory::collect!(ComponentInfo);
+
+/// Warn when a component's response doesn't match its declared root
+///
+/// Called by `#[component]` in debug builds for components with
+/// `root = "..."`. With an `outerHTML` swap the response must be the root
+/// element itself; with other swaps it must not repeat the root's id, which
+/// would duplicate it in the page.
+pub async fn check_root<C: ComponentName>(response: Response) -> Response {
+    let Some(root) = C::ROOT else {
+        return response;
+    };
+    let is_html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/html"));
+    let streamed = response.extensions().get::<crate::Streamed>().is_some();
+    if response.status() != StatusCode::OK || !is_html || streamed {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return Response::from_parts(parts, Body::empty());
+    };
+    let html = String::from_utf8_lossy(&bytes);
+    let rendered = first_element(&html).map(|tag| element_id(tag));
+
+    let swap = C::SWAP.unwrap_or(Swap::OuterHtml);
+    let problem = match (swap, rendered) {
+        // Empty responses remove the target, that's fine
+        (_, None) => None,
+        (Swap::OuterHtml, Some(Some(id))) if id == root => None,
+        (Swap::OuterHtml, Some(Some(id))) => Some(format!("renders #{} instead", id)),
+        (Swap::OuterHtml, Some(None)) => Some("renders a root element without id".to_string()),
+        (_, Some(Some(id))) if id == root => Some(format!(
+            "renders the root itself, which {} nests inside the existing one",
+            swap
+        )),
+        _ => None,
+    };
+    if let Some(problem) = problem {
+        tracing::warn!(
+            "component {} declares root #{} but {}",
+            C::name(),
+            root,
+            problem
+        );
+    }
+
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// Start tag contents of the first element, skipping comments and doctypes
+pub(crate) fn first_element(html: &str) -> Option<&str> {
+    let mut rest = html.trim_start();
+    loop {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.split_once("-->")?.1.trim_start();
+        } else if rest.starts_with("<!") {
+            rest = rest.split_once('>')?.1.trim_start();
+        } else {
+            break;
+        }
+    }
+    let tag = rest.strip_prefix('<')?;
+    tag.split_once('>').map(|(tag, _)| tag)
+}
+
+fn element_id(tag: &str) -> Option<&str> {
+    static ID: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r#"\sid\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).expect("valid regex")
+    });
+    let captures = ID.captures(tag)?;
+    (1..=3)
+        .find_map(|group| captures.get(group))
+        .map(|m| m.as_str())
+}
@@ -0,0 +1,352 @@
+//! Rendering components inline from a page
+//!
+//! A page embedding a component should render it exactly as the component's
+//! own route would for the same URL, so the initial page and later partials
+//! agree. [`RequestContext`] captures the page request and runs the
+//! component's handler against it:
+//!
+//! ```rust,ignore
+//! #[page("/")]
+//! async fn index_page(ctx: RequestContext) -> Page {
+//!     html! {
+//!         body { (render!(ctx, TodoList)) }
+//!     }
+//!     .into()
+//! }
+//! ```
+//!
+//! The component sees the page's query string, cookies and extensions: view
+//! state is hydrated (and persisted) as for an htmx request issued by the
+//! page, and `url.build_main_url()` points back at the page.
+//!
+//! Components run in their own task. [`RequestContext::render_all`] renders
+//! several at once, and [`RequestContext::boundary`] substitutes a fallback
+//! with a retry button when one fails, panics or times out, so a single
+//! broken component doesn't take down the page.
+
+use crate::base_path::BasePrefix;
+use crate::component::ComponentInfo;
+use crate::hx::{self, Swap};
+use crate::url_builder::{ComponentName, UrlBuilder};
+use axum::{
+    Router,
+    body::Body,
+    extract::FromRequestParts,
+    http::{Extensions, HeaderMap, HeaderValue, Method, Request, StatusCode, Uri, request::Parts},
+    routing::any,
+};
+use maud::{Markup, PreEscaped, Render, html};
+use std::convert::Infallible;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+
+/// Marks a request synthesized by [`RequestContext::render`], so view state
+/// upgrades don't answer it with a redirect
+#[derive(Clone, Copy)]
+pub(crate) struct InlineRender;
+
+/// The current request, for rendering components inline
+#[derive(Clone, Debug)]
+pub struct RequestContext {
+    uri: Uri,
+    headers: HeaderMap,
+    extensions: Extensions,
+}
+
+impl RequestContext {
+    /// Query string of the request, `""` without one
+    pub fn query(&self) -> &str {
+        self.uri.query().unwrap_or("")
+    }
+
+    /// Headers of the request
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// `UrlBuilder` for the current page, carrying its query params
+    pub fn url(&self) -> UrlBuilder {
+        UrlBuilder::from_parts(self.uri.path(), &self.parts())
+    }
+
+    /// Render a component with the current request, as its route would
+    ///
+    /// Use [`render!`](crate::render) in markup. Renders nothing, and logs
+    /// the error, if the component fails; see [`boundary`](Self::boundary)
+    /// for a fallback. Components declared `#[component(lazy)]` render their
+    /// placeholder, see [`lazy`](mod@crate::lazy).
+    pub async fn render<C: ComponentName>(&self, component: C) -> Markup {
+        let boundary = self.boundary(component);
+        if boundary.lazy {
+            return boundary.placeholder();
+        }
+        boundary.fetch().await.unwrap_or_else(|error| {
+            tracing::warn!("inline render of {} failed: {}", C::name(), error);
+            PreEscaped(String::new())
+        })
+    }
+
+    /// Render a component, reporting failures instead of hiding them
+    ///
+    /// Renders lazy components in full.
+    pub async fn try_render<C: ComponentName>(&self, component: C) -> Result<Markup, RenderError> {
+        self.boundary(component).fetch().await
+    }
+
+    /// Render a component behind an error boundary
+    ///
+    /// If the component fails, panics or exceeds the timeout, a fallback
+    /// with a retry button is rendered in its place instead of failing the
+    /// page:
+    ///
+    /// ```rust,ignore
+    /// (ctx.boundary(Stats).timeout(Duration::from_millis(500)).render().await)
+    /// ```
+    pub fn boundary<C: ComponentName>(&self, component: C) -> Boundary {
+        let info = crate::component::component_info(C::name());
+        let path = info.map(|info| info.path).unwrap_or_default();
+        let retry = UrlBuilder::from_parts(path, &self.parts()).for_component(component);
+        Boundary {
+            ctx: self.clone(),
+            name: C::name(),
+            info,
+            root: C::ROOT,
+            lazy: C::LAZY,
+            retry,
+            timeout: None,
+            fallback: None,
+        }
+    }
+
+    /// Render several components concurrently
+    ///
+    /// ```rust,ignore
+    /// let [list, stats] = ctx
+    ///     .render_all([ctx.boundary(TodoList), ctx.boundary(Stats).timeout(limit)])
+    ///     .await;
+    /// ```
+    pub async fn render_all<const N: usize>(&self, boundaries: [Boundary; N]) -> [Markup; N] {
+        let tasks = boundaries.map(|boundary| tokio::spawn(boundary.render()));
+        let mut rendered = Vec::with_capacity(N);
+        for task in tasks {
+            // Boundaries catch component panics, so this only fails on shutdown
+            rendered.push(task.await.unwrap_or_else(|_| PreEscaped(String::new())));
+        }
+        rendered
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("one result per boundary"))
+    }
+
+    /// Request parts the context was captured from, without the body
+    fn parts(&self) -> Parts {
+        let mut parts = Request::new(()).into_parts().0;
+        parts.uri = self.uri.clone();
+        parts.headers = self.headers.clone();
+        parts.extensions = self.extensions.clone();
+        parts
+    }
+
+    /// Request for a component route, issued on behalf of this page
+    fn request(&self, path: &str) -> Request<Body> {
+        let uri = match self.uri.query() {
+            Some(query) => format!("{}?{}", path, query),
+            None => path.to_string(),
+        };
+
+        let mut request = Request::new(Body::empty());
+        *request.method_mut() = Method::GET;
+        *request.uri_mut() = uri.parse().unwrap_or_default();
+        *request.headers_mut() = self.headers.clone();
+        *request.extensions_mut() = self.extensions.clone();
+        request.extensions_mut().insert(InlineRender);
+        // The component is rendered on behalf of this page, as with htmx requests
+        if let Ok(page_url) = HeaderValue::from_str(&self.page_url()) {
+            request.headers_mut().insert("HX-Current-URL", page_url);
+        }
+        request
+    }
+
+    /// URL of the page as the browser sees it
+    fn page_url(&self) -> String {
+        let base = BasePrefix::from_extensions(&self.extensions);
+        let path = crate::base_path::join(base, self.uri.path());
+        match self.uri.query() {
+            Some(query) => format!("{}?{}", path, query),
+            None => path,
+        }
+    }
+}
+
+/// Why an embedded component could not be rendered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderError {
+    /// No component with this name is registered
+    NotRegistered,
+    /// The component answered with an error status
+    Status(StatusCode),
+    /// The component took longer than the boundary's timeout
+    Timeout(Duration),
+    /// The component panicked
+    Panicked,
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::NotRegistered => f.write_str("component is not registered"),
+            RenderError::Status(status) => write!(f, "component responded with {}", status),
+            RenderError::Timeout(limit) => write!(f, "component timed out after {:?}", limit),
+            RenderError::Panicked => f.write_str("component panicked"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+/// Markup rendered in place of a failed component, given the error and a
+/// `UrlBuilder` for the component's route to retry with
+pub type Fallback = Arc<dyn Fn(&RenderError, UrlBuilder) -> Markup + Send + Sync>;
+
+/// An embedded component with a timeout and fallback, see
+/// [`RequestContext::boundary`]
+#[derive(Clone)]
+pub struct Boundary {
+    ctx: RequestContext,
+    name: &'static str,
+    info: Option<&'static ComponentInfo>,
+    root: Option<&'static str>,
+    lazy: bool,
+    retry: UrlBuilder,
+    timeout: Option<Duration>,
+    fallback: Option<Fallback>,
+}
+
+impl Boundary {
+    /// Give up on the component after `limit`
+    pub fn timeout(mut self, limit: Duration) -> Self {
+        self.timeout = Some(limit);
+        self
+    }
+
+    /// Render custom markup when the component fails
+    ///
+    /// ```rust,ignore
+    /// ctx.boundary(Stats).fallback(|_, retry| html! {
+    ///     (hx::get(retry).trigger(Trigger::Load).element("div", &[], Some(html! { "Loading…" })))
+    /// })
+    /// ```
+    pub fn fallback(
+        mut self,
+        fallback: impl Fn(&RenderError, UrlBuilder) -> Markup + Send + Sync + 'static,
+    ) -> Self {
+        self.fallback = Some(Arc::new(fallback));
+        self
+    }
+
+    /// Render the component, or the fallback if it fails
+    ///
+    /// Lazy components render their placeholder.
+    pub async fn render(self) -> Markup {
+        if self.lazy {
+            return self.placeholder();
+        }
+        match self.fetch().await {
+            Ok(markup) => markup,
+            Err(error) => {
+                tracing::warn!("inline render of {} failed: {}", self.name, error);
+                match &self.fallback {
+                    Some(fallback) => fallback(&error, self.retry.clone()),
+                    None => default_fallback(self.root, self.retry.clone()),
+                }
+            }
+        }
+    }
+
+    fn placeholder(&self) -> Markup {
+        crate::lazy::lazy(self.retry.clone()).render()
+    }
+
+    /// Markup shown while the component renders, see `StreamingPage::slot`
+    pub(crate) fn loading(&self) -> Markup {
+        crate::lazy::loading(&self.retry)
+    }
+
+    /// Run the component's handler in its own task, so panics stay contained
+    async fn fetch(&self) -> Result<Markup, RenderError> {
+        let info = self.info.ok_or(RenderError::NotRegistered)?;
+        let mut request = self.ctx.request(self.retry.route_path());
+        // Dispatch through a router so `Path` extractors see the component's
+        // params. The page's extensions are added after routing, as axum
+        // would otherwise append its params to the page's own.
+        let extensions = std::mem::take(request.extensions_mut());
+        let handler = info.handler;
+        let router = Router::new().route(
+            info.path,
+            any(move |mut request: Request<Body>| {
+                let mut merged = extensions.clone();
+                merged.extend(std::mem::take(request.extensions_mut()));
+                *request.extensions_mut() = merged;
+                handler(request)
+            }),
+        );
+        let mut task = tokio::spawn(async move {
+            let Ok(response) = router.oneshot(request).await;
+            if !response.status().is_success() {
+                return Err(RenderError::Status(response.status()));
+            }
+            axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .map(|bytes| PreEscaped(String::from_utf8_lossy(&bytes).into_owned()))
+                .map_err(|_| RenderError::Status(StatusCode::INTERNAL_SERVER_ERROR))
+        });
+
+        let joined = match self.timeout {
+            Some(limit) => match tokio::time::timeout(limit, &mut task).await {
+                Ok(joined) => joined,
+                Err(_) => {
+                    task.abort();
+                    return Err(RenderError::Timeout(limit));
+                }
+            },
+            None => task.await,
+        };
+        joined.map_err(|_| RenderError::Panicked)?
+    }
+}
+
+/// Notice with a button re-requesting the component in place
+fn default_fallback(root: Option<&str>, retry: UrlBuilder) -> Markup {
+    html! {
+        div id=[root] .htmoxide-error role="alert" {
+            "Failed to load. "
+            (hx::get(retry)
+                .target("closest .htmoxide-error")
+                .swap(Swap::OuterHtml)
+                .button("Retry"))
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for RequestContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(RequestContext {
+            uri: parts.uri.clone(),
+            headers: parts.headers.clone(),
+            extensions: parts.extensions.clone(),
+        })
+    }
+}
+
+/// Render a component inline from a page: `render!(ctx, TodoList)`
+///
+/// Shorthand for `ctx.render(TodoList).await`, see [`RequestContext`].
+#[macro_export]
+macro_rules! render {
+    ($ctx:expr, $component:expr) => {
+        $ctx.render($component).await
+    };
+}
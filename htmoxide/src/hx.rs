@@ -0,0 +1,425 @@
+//! Typed htmx attributes
+//!
+//! Builds the `hx-*` attribute set of an interactive element from a
+//! `UrlBuilder`, so request and push URLs always carry the same params:
+//!
+//! ```rust
+//! use htmoxide::hx::{self, Swap};
+//! use htmoxide::prelude::*;
+//!
+//! let url = UrlBuilder::new("/todo_container", "");
+//! # let _ =
+//! html! {
+//!     li {
+//!         (hx::get(url.with_params([("filter", "active")]))
+//!             .target("#todo-container")
+//!             .swap(Swap::OuterHtml)
+//!             .push_main()
+//!             .a("Active"))
+//!     }
+//! };
+//! ```
+//!
+//! renders
+//!
+//! ```html
+//! <a href="/?filter=active" hx-get="/todo_container?filter=active"
+//!    hx-target="#todo-container" hx-swap="outerHTML" hx-push-url="/?filter=active">Active</a>
+//! ```
+//!
+//! Links get an `href` and `GET`/`POST` forms an `action` so they keep
+//! working without JavaScript: a link pushing the main page falls back to
+//! that page. HTML forms can't send `PUT`, `PATCH` or `DELETE`, so those
+//! forms get only the `hx-*` attributes rather than a fallback hitting the
+//! wrong route.
+//!
+//! Components declaring `#[component(root = "...", swap = "...")]` are
+//! targeted and swapped that way unless `target()` and `swap()` say otherwise.
+
+use crate::url_builder::UrlBuilder;
+use maud::{Escaper, Markup, PreEscaped, Render};
+use serde::Serialize;
+use std::fmt::{self, Write};
+use std::time::Duration;
+
+/// `hx-get` request to `url`
+pub fn get(url: UrlBuilder) -> Hx {
+    Hx::new("get", url)
+}
+
+/// `hx-post` request to `url`
+pub fn post(url: UrlBuilder) -> Hx {
+    Hx::new("post", url)
+}
+
+/// `hx-put` request to `url`
+pub fn put(url: UrlBuilder) -> Hx {
+    Hx::new("put", url)
+}
+
+/// `hx-patch` request to `url`
+pub fn patch(url: UrlBuilder) -> Hx {
+    Hx::new("patch", url)
+}
+
+/// `hx-delete` request to `url`
+pub fn delete(url: UrlBuilder) -> Hx {
+    Hx::new("delete", url)
+}
+
+/// How the response replaces the target (`hx-swap`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Swap {
+    InnerHtml,
+    OuterHtml,
+    BeforeBegin,
+    AfterBegin,
+    BeforeEnd,
+    AfterEnd,
+    Delete,
+    None,
+}
+
+impl fmt::Display for Swap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Swap::InnerHtml => "innerHTML",
+            Swap::OuterHtml => "outerHTML",
+            Swap::BeforeBegin => "beforebegin",
+            Swap::AfterBegin => "afterbegin",
+            Swap::BeforeEnd => "beforeend",
+            Swap::AfterEnd => "afterend",
+            Swap::Delete => "delete",
+            Swap::None => "none",
+        })
+    }
+}
+
+/// What starts the request (`hx-trigger`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Trigger {
+    Click,
+    Change,
+    Submit,
+    Load,
+    Revealed,
+    /// `intersect once`, when the element enters a scrolled container's view
+    Intersect,
+    /// `keyup changed delay:..`, for search-as-you-type inputs
+    Typing(Duration),
+    /// `every ..`, polling
+    Every(Duration),
+    /// Any other trigger specification, e.g. `"click from:body"`
+    Custom(String),
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trigger::Click => f.write_str("click"),
+            Trigger::Change => f.write_str("change"),
+            Trigger::Submit => f.write_str("submit"),
+            Trigger::Load => f.write_str("load"),
+            Trigger::Revealed => f.write_str("revealed"),
+            Trigger::Intersect => f.write_str("intersect once"),
+            Trigger::Typing(delay) => write!(f, "keyup changed delay:{}", timing(*delay)),
+            Trigger::Every(interval) => write!(f, "every {}", timing(*interval)),
+            Trigger::Custom(spec) => f.write_str(spec),
+        }
+    }
+}
+
+/// htmx time value: `2s` for whole seconds, `1500ms` otherwise
+fn timing(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    if millis > 0 && millis.is_multiple_of(1000) {
+        format!("{}s", millis / 1000)
+    } else {
+        format!("{}ms", millis)
+    }
+}
+
+/// Where the browser history points after the request
+#[derive(Clone, Debug)]
+enum Push {
+    /// The main page with the request's params
+    Main,
+    Url(String),
+    Disabled,
+}
+
+/// Attribute set of an htmx-driven element, rendered by the element methods
+#[derive(Clone)]
+pub struct Hx {
+    method: &'static str,
+    url: UrlBuilder,
+    target: Option<String>,
+    swap: Option<String>,
+    trigger: Option<String>,
+    push: Option<Push>,
+    replace_main: bool,
+    vals: Option<String>,
+    confirm: Option<String>,
+    indicator: Option<String>,
+    attrs: Vec<(String, Option<String>)>,
+}
+
+impl Hx {
+    fn new(method: &'static str, url: UrlBuilder) -> Self {
+        Self {
+            method,
+            target: url.default_target(),
+            swap: url.default_swap().map(|swap| swap.to_string()),
+            url,
+            trigger: None,
+            push: None,
+            replace_main: false,
+            vals: None,
+            confirm: None,
+            indicator: None,
+            attrs: Vec::new(),
+        }
+    }
+
+    /// CSS selector of the element to swap (`hx-target`)
+    pub fn target(mut self, selector: impl Into<String>) -> Self {
+        self.target = Some(selector.into());
+        self
+    }
+
+    pub fn swap(mut self, swap: Swap) -> Self {
+        self.swap = Some(swap.to_string());
+        self
+    }
+
+    /// Swap with modifiers, e.g. `swap_with(Swap::OuterHtml, "transition:true")`
+    pub fn swap_with(mut self, swap: Swap, modifiers: &str) -> Self {
+        self.swap = Some(format!("{} {}", swap, modifiers));
+        self
+    }
+
+    pub fn trigger(mut self, trigger: Trigger) -> Self {
+        self.trigger = Some(trigger.to_string());
+        self
+    }
+
+    /// Push the main page URL with the request's params, see
+    /// `UrlBuilder::build_main_url`
+    pub fn push_main(mut self) -> Self {
+        self.push = Some(Push::Main);
+        self
+    }
+
+    /// Push a specific URL
+    pub fn push_url(mut self, url: impl Into<String>) -> Self {
+        self.push = Some(Push::Url(url.into()));
+        self
+    }
+
+    /// Keep the current URL, overriding an inherited `hx-push-url`
+    pub fn no_push(mut self) -> Self {
+        self.push = Some(Push::Disabled);
+        self
+    }
+
+    /// Replace the main page URL instead of adding a history entry
+    pub fn replace_main(mut self) -> Self {
+        self.replace_main = true;
+        self
+    }
+
+    /// Extra values sent with the request (`hx-vals`), serialized as JSON
+    pub fn vals(mut self, vals: impl Serialize) -> Self {
+        self.vals = serde_json::to_string(&vals).ok();
+        self
+    }
+
+    /// Ask for confirmation before sending (`hx-confirm`)
+    pub fn confirm(mut self, message: impl Into<String>) -> Self {
+        self.confirm = Some(message.into());
+        self
+    }
+
+    /// Element shown while the request is in flight (`hx-indicator`)
+    pub fn indicator(mut self, selector: impl Into<String>) -> Self {
+        self.indicator = Some(selector.into());
+        self
+    }
+
+    pub fn id(self, id: impl Into<String>) -> Self {
+        self.attr("id", id)
+    }
+
+    pub fn class(self, class: impl Into<String>) -> Self {
+        self.attr("class", class)
+    }
+
+    /// Any other attribute; the value is escaped
+    pub fn attr(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attrs.push((name.into(), Some(value.into())));
+        self
+    }
+
+    /// A boolean attribute such as `checked`, present when `on` is true
+    pub fn flag(mut self, name: impl Into<String>, on: bool) -> Self {
+        if on {
+            self.attrs.push((name.into(), None));
+        }
+        self
+    }
+
+    /// Request URL
+    pub fn url(&self) -> String {
+        self.url.clone().build()
+    }
+
+    /// `<a>` with an `href` to the pushed page, or to the request URL of a
+    /// `GET`
+    ///
+    /// Links can only `GET`, so other requests without a pushed page get no
+    /// `href` and need htmx.
+    pub fn a(self, content: impl Render) -> Markup {
+        let href = match (&self.push, self.method) {
+            (Some(Push::Main), _) => Some(self.url.clone().build_main_url()),
+            (Some(Push::Url(url)), _) => Some(url.clone()),
+            (_, "get") => Some(self.url()),
+            _ => None,
+        };
+        let fallback: Vec<_> = href.map(|href| ("href", href)).into_iter().collect();
+        self.element("a", &fallback, Some(content.render()))
+    }
+
+    pub fn button(self, content: impl Render) -> Markup {
+        self.element("button", &[], Some(content.render()))
+    }
+
+    /// `<form>` with `action` and `method` for submissions without JavaScript
+    ///
+    /// Only `GET` and `POST` forms get the fallback; `PUT`, `PATCH` and
+    /// `DELETE` need htmx.
+    pub fn form(self, content: impl Render) -> Markup {
+        let fallback = match self.method {
+            "get" => vec![("action", self.url())],
+            "post" => vec![("action", self.url()), ("method", "post".to_string())],
+            _ => Vec::new(),
+        };
+        self.element("form", &fallback, Some(content.render()))
+    }
+
+    /// Void `<input>`; set `type`, `name` and the like with `attr`
+    pub fn input(self) -> Markup {
+        self.element("input", &[], None)
+    }
+
+    /// Any element; `None` content renders a void element
+    pub fn element(
+        self,
+        tag: &str,
+        fallback: &[(&str, String)],
+        content: Option<Markup>,
+    ) -> Markup {
+        let mut html = String::new();
+        html.push('<');
+        html.push_str(tag);
+        for (name, value) in fallback {
+            push_attr(&mut html, name, Some(value));
+        }
+        for (name, value) in self.attributes() {
+            push_attr(&mut html, &name, Some(&value));
+        }
+        for (name, value) in &self.attrs {
+            push_attr(&mut html, name, value.as_deref());
+        }
+        html.push('>');
+        if let Some(content) = content {
+            html.push_str(&content.into_string());
+            html.push_str("</");
+            html.push_str(tag);
+            html.push('>');
+        }
+        PreEscaped(html)
+    }
+
+    /// The `hx-*` attributes as `(name, value)` pairs
+    pub fn attributes(&self) -> Vec<(String, String)> {
+        let mut attrs = vec![(format!("hx-{}", self.method), self.url())];
+        let mut push = |name: &str, value: Option<&String>| {
+            if let Some(value) = value {
+                attrs.push((name.to_string(), value.clone()));
+            }
+        };
+        push("hx-target", self.target.as_ref());
+        push("hx-swap", self.swap.as_ref());
+        push("hx-trigger", self.trigger.as_ref());
+        let pushed = match &self.push {
+            Some(Push::Main) => Some(self.url.clone().build_main_url()),
+            Some(Push::Url(url)) => Some(url.clone()),
+            Some(Push::Disabled) => Some("false".to_string()),
+            None => None,
+        };
+        if self.replace_main {
+            push(
+                "hx-replace-url",
+                Some(&pushed.unwrap_or_else(|| self.url.clone().build_main_url())),
+            );
+        } else {
+            push("hx-push-url", pushed.as_ref());
+        }
+        push("hx-vals", self.vals.as_ref());
+        push("hx-confirm", self.confirm.as_ref());
+        push("hx-indicator", self.indicator.as_ref());
+        attrs
+    }
+}
+
+pub(crate) fn push_attr(html: &mut String, name: &str, value: Option<&str>) {
+    html.push(' ');
+    html.push_str(name);
+    if let Some(value) = value {
+        html.push_str("=\"");
+        // Writing to a String can't fail
+        let _ = Escaper::new(html).write_str(value);
+        html.push('"');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forms_fall_back_only_for_get_and_post() {
+        let url = || UrlBuilder::new("/todos/7", "");
+
+        assert_eq!(
+            get(url()).form("").into_string(),
+            r#"<form action="/todos/7" hx-get="/todos/7"></form>"#
+        );
+        assert_eq!(
+            post(url()).form("").into_string(),
+            r#"<form action="/todos/7" method="post" hx-post="/todos/7"></form>"#
+        );
+        assert_eq!(
+            delete(url()).form("").into_string(),
+            r#"<form hx-delete="/todos/7"></form>"#
+        );
+    }
+
+    #[test]
+    fn links_fall_back_only_for_get_or_a_pushed_page() {
+        let url = || UrlBuilder::new("/todos/7", "");
+
+        assert_eq!(
+            get(url()).a("Open").into_string(),
+            r#"<a href="/todos/7" hx-get="/todos/7">Open</a>"#
+        );
+        assert_eq!(
+            delete(url()).a("Delete").into_string(),
+            r#"<a hx-delete="/todos/7">Delete</a>"#
+        );
+        assert_eq!(
+            delete(url()).push_url("/todos").a("Delete").into_string(),
+            r#"<a href="/todos" hx-delete="/todos/7" hx-push-url="/todos">Delete</a>"#
+        );
+    }
+}
@@ -0,0 +1,50 @@
+//! Field introspection for view state types
+//!
+//! Serde's derived `Deserialize` passes the names of a struct's fields to the
+//! deserializer before reading anything. [`field_names`] runs a deserializer
+//! that only records that list, so the query keys a state reads are known
+//! without a value of the type or any extra derive. Names follow
+//! `#[serde(rename)]` and `rename_all`, in declaration order.
+//!
+//! Types that deserialize from a map rather than a struct (for example with
+//! `#[serde(flatten)]` fields or a custom `Deserialize`) report no fields.
+
+use serde::de::{self, Deserialize, Deserializer, Visitor, value::Error};
+
+/// Field names a type reads when deserialized from query params
+pub fn field_names<'de, T: Deserialize<'de>>() -> Option<&'static [&'static str]> {
+    let mut fields = None;
+    let _ = T::deserialize(FieldNames {
+        fields: &mut fields,
+    });
+    fields
+}
+
+/// Deserializer that records the field list of a struct and then bails out
+struct FieldNames<'a> {
+    fields: &'a mut Option<&'static [&'static str]>,
+}
+
+impl<'de> Deserializer<'de> for FieldNames<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        *self.fields = Some(fields);
+        Err(de::Error::custom("field names recorded"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
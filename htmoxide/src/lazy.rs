@@ -0,0 +1,131 @@
+//! Lazily loaded components
+//!
+//! Slow widgets shouldn't hold up the page. A placeholder renders right away
+//! and loads the component with `hx-get` once it is on the page, replacing
+//! itself with the response:
+//!
+//! ```rust
+//! use htmoxide::prelude::*;
+//!
+//! let url = UrlBuilder::new("/stats", "filter=active");
+//! # let _ =
+//! html! {
+//!     aside { (lazy(url).revealed().indicator("#spinner")) }
+//! };
+//! ```
+//!
+//! renders
+//!
+//! ```html
+//! <div hx-get="/stats?filter=active" hx-target="this" hx-swap="outerHTML"
+//!      hx-trigger="revealed" hx-push-url="false" hx-indicator="#spinner"
+//!      class="htmoxide-lazy" aria-busy="true"><span class="htmoxide-loading">Loading…</span></div>
+//! ```
+//!
+//! The URL keeps the page's params, so the component loads with the current
+//! view state. Components can make every embedding lazy and bring their own
+//! skeleton:
+//!
+//! ```rust,ignore
+//! fn stats_skeleton() -> Markup {
+//!     html! { div .skeleton { "Crunching numbers…" } }
+//! }
+//!
+//! #[component(lazy, placeholder = stats_skeleton, root = "stats")]
+//! async fn stats(state: TodoState, url: UrlBuilder) -> Html { /* ... */ }
+//!
+//! // Renders the skeleton now, the component after the page loaded
+//! (render!(ctx, Stats))
+//! // Same, for a component without `lazy`
+//! (lazy(ctx.url().for_component(Stats)))
+//! ```
+//!
+//! A component declaring a root gets it as the placeholder's id, so it can
+//! be targeted before it has loaded.
+
+use crate::hx::{self, Swap, Trigger};
+use crate::url_builder::UrlBuilder;
+use maud::{Markup, Render, html};
+
+/// Placeholder loading the component at `url`, see the [module docs](self)
+pub fn lazy(url: UrlBuilder) -> Placeholder {
+    Placeholder {
+        url,
+        trigger: Trigger::Load,
+        indicator: None,
+        content: None,
+    }
+}
+
+/// Markup standing in for a component until it loads
+#[derive(Clone)]
+pub struct Placeholder {
+    url: UrlBuilder,
+    trigger: Trigger,
+    indicator: Option<String>,
+    content: Option<Markup>,
+}
+
+impl Placeholder {
+    /// When to load the component, `Trigger::Load` by default
+    pub fn trigger(mut self, trigger: Trigger) -> Self {
+        self.trigger = trigger;
+        self
+    }
+
+    /// Load once the placeholder is scrolled into view
+    pub fn revealed(self) -> Self {
+        self.trigger(Trigger::Revealed)
+    }
+
+    /// Load once the placeholder enters the view of a scrolled container
+    pub fn intersect(self) -> Self {
+        self.trigger(Trigger::Intersect)
+    }
+
+    /// Element shown while the component loads (`hx-indicator`)
+    pub fn indicator(mut self, selector: impl Into<String>) -> Self {
+        self.indicator = Some(selector.into());
+        self
+    }
+
+    /// Markup shown until the component loads, instead of the component's
+    /// `placeholder` or a "Loading…" notice
+    pub fn content(mut self, content: impl Render) -> Self {
+        self.content = Some(content.render());
+        self
+    }
+}
+
+/// The component's placeholder markup, or a "Loading…" notice
+pub(crate) fn loading(url: &UrlBuilder) -> Markup {
+    match url.placeholder() {
+        Some(placeholder) => placeholder(),
+        None => html! { span .htmoxide-loading { "Loading…" } },
+    }
+}
+
+impl Render for Placeholder {
+    fn render(&self) -> Markup {
+        let content = match &self.content {
+            Some(content) => content.clone(),
+            None => loading(&self.url),
+        };
+
+        // The response replaces the placeholder, whatever the component's own swap
+        let mut hx = hx::get(self.url.clone())
+            .target("this")
+            .swap(Swap::OuterHtml)
+            .trigger(self.trigger.clone())
+            .no_push();
+        if let Some(indicator) = &self.indicator {
+            hx = hx.indicator(indicator.clone());
+        }
+        if let Some(root) = self.url.root() {
+            hx = hx.id(root);
+        }
+        hx.class("htmoxide-lazy")
+            .attr("aria-busy", "true")
+            .element("div", &[], Some(content))
+    }
+}
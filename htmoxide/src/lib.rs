@@ -1,26 +1,55 @@
 pub mod app;
+pub mod base_path;
 pub mod body;
 pub mod client_helpers;
+pub mod compact;
 pub mod component;
+pub mod context;
+pub mod hx;
+pub mod introspect;
+pub mod lazy;
+pub mod persist;
+pub mod poll;
+pub mod query;
 pub mod response;
+pub mod signed;
 pub mod state;
 pub mod state_loader;
 pub mod state_urls_middleware;
+pub mod store;
+pub mod stream;
 pub mod url_builder;
+pub mod view_state;
 
 #[cfg(feature = "qs-forms")]
 pub mod qs_form;
 
 pub use app::{HtmxRouterExt, RouterExt, app};
+pub use base_path::{BasePath, BasePrefix, PublicOrigin};
 pub use body::Body;
-pub use client_helpers::{clear_input_handler, cookie_cleaner_script, preserve_params};
+#[allow(deprecated)]
+pub use client_helpers::cookie_cleaner_script;
+pub use client_helpers::{clear_input_handler, preserve_params, state_fields, state_fields_vals};
 pub use component::{Component, ComponentInfo};
-pub use htmoxide_macros::component;
+pub use context::{Boundary, RenderError, RequestContext};
+pub use htmoxide_macros::{component, page, view_state};
+pub use hx::{Hx, Swap, Trigger};
+pub use lazy::{Placeholder, lazy};
+pub use persist::{Persist, PersistConfig, StateScope};
+pub use poll::{Poll, Polling};
+pub use query::QueryMap;
 pub use response::{Html, Page};
-pub use state::StateExtractor;
+pub use signed::{SignatureError, Signed, SigningKey};
+pub use state::{FIELDS_PARAM, StateExtractor, hydrate};
 pub use state_loader::StateLoader;
-pub use state_urls_middleware::{StateUrlsConfig, state_urls_middleware_impl};
-pub use url_builder::{ComponentName, UrlBuilder};
+pub use state_urls_middleware::{
+    CookieDecision, CookieRule, ExclusionReason, StateUrlsConfig, StateUrlsMode,
+    state_urls_middleware_impl,
+};
+pub use store::StateStore;
+pub use stream::{Slot, Streamed, StreamingPage};
+pub use url_builder::{ComponentName, PageName, UnresolvedPath, UrlBuilder};
+pub use view_state::{StateMigration, ViewStateInfo};
 
 #[cfg(feature = "qs-forms")]
 pub use qs_form::QsForm;
@@ -39,13 +68,27 @@ pub use tower_cookies;
 
 pub mod prelude {
     pub use crate::app::{HtmxRouterExt, app};
+    pub use crate::base_path::BasePrefix;
     pub use crate::body::Body;
-    pub use crate::client_helpers::{clear_input_handler, cookie_cleaner_script, preserve_params};
+    #[allow(deprecated)]
+    pub use crate::client_helpers::cookie_cleaner_script;
+    pub use crate::client_helpers::{
+        clear_input_handler, preserve_params, state_fields, state_fields_vals,
+    };
     pub use crate::component;
+    pub use crate::context::RequestContext;
+    pub use crate::hx::{self, Hx, Swap, Trigger};
+    pub use crate::lazy::lazy;
+    pub use crate::page;
+    pub use crate::persist::{Persist, PersistConfig, StateScope};
+    pub use crate::poll::Poll;
+    pub use crate::render;
     pub use crate::response::{Html, Page};
     pub use crate::state_loader::StateLoader;
-    pub use crate::state_urls_middleware::StateUrlsConfig;
+    pub use crate::state_urls_middleware::{CookieRule, StateUrlsConfig, StateUrlsMode};
+    pub use crate::stream::StreamingPage;
     pub use crate::url_builder::UrlBuilder;
+    pub use crate::view_state;
 
     #[cfg(feature = "qs-forms")]
     pub use crate::qs_form::QsForm;
@@ -0,0 +1,328 @@
+//! Runtime configuration for view state persistence
+//!
+//! Persistence used to be a compile-time `persist-state` feature that the
+//! component macro expanded into every handler. It is now configured on the
+//! app and can be overridden per component:
+//!
+//! ```rust,ignore
+//! let app = htmoxide::app()
+//!     .route("/", get(index_page))
+//!     .htmx()
+//!     .persist_state(Persist::Cookie);
+//!
+//! #[component(persist = "none")]
+//! async fn search(state: SearchState, url: UrlBuilder) -> Html { /* ... */ }
+//! ```
+//!
+//! See [`crate::store`] for the available storage backends.
+//!
+//! # Cookie attributes and scoping
+//!
+//! Cookies written for persisted state (including the session id cookie) use
+//! the attributes configured on [`PersistConfig`]. With [`StateScope::Page`],
+//! stored keys are namespaced by the page the component is rendered on, so
+//! `/projects` and `/users` can both persist a `sort` field:
+//!
+//! ```rust,ignore
+//! use htmoxide::{Persist, PersistConfig, StateScope};
+//! use htmoxide::tower_cookies::cookie::SameSite;
+//!
+//! let config = PersistConfig::new(Persist::Cookie)
+//!     .same_site(SameSite::Lax)
+//!     .secure(true)
+//!     .http_only(true)
+//!     .max_age(Duration::from_secs(30 * 24 * 60 * 60))
+//!     .scope(StateScope::Page);
+//! ```
+//!
+//! Scoping uses the cookie name rather than the cookie `Path`, because
+//! component routes are usually not nested under the page that renders them.
+
+use crate::store::{CookieStore, SessionStore, StateStore};
+use axum::http::{Extensions, request::Parts};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tower_cookies::cookie::SameSite;
+use tower_cookies::{Cookie, Cookies};
+
+/// Separator between the page scope and the field name in stored keys
+pub const SCOPE_SEPARATOR: char = '~';
+
+// Used when the app has no `persist_state()` layer, shared so that
+// component-level `persist = "session"` keeps its sessions across requests
+static DEFAULT_CONFIG: Lazy<PersistConfig> = Lazy::new(PersistConfig::default);
+
+/// Where a component's view state is persisted between requests
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Persist {
+    /// State lives in the URL only
+    #[default]
+    None,
+    /// State is stored client-side in cookies
+    Cookie,
+    /// State is stored on the server, keyed by a session cookie
+    Session,
+}
+
+/// How persisted keys are shared between pages
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StateScope {
+    /// Every page shares every key
+    #[default]
+    Global,
+    /// Keys are namespaced by the page path
+    Page,
+}
+
+/// Attributes applied to every cookie written for persisted state
+#[derive(Clone, Debug)]
+pub struct CookieAttributes {
+    pub path: String,
+    pub domain: Option<String>,
+    pub same_site: Option<SameSite>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub max_age: Option<Duration>,
+}
+
+impl Default for CookieAttributes {
+    fn default() -> Self {
+        Self {
+            path: "/".to_string(),
+            domain: None,
+            same_site: Some(SameSite::Lax),
+            secure: false,
+            http_only: false,
+            max_age: None,
+        }
+    }
+}
+
+impl CookieAttributes {
+    /// Create a cookie with these attributes
+    pub fn cookie(&self, name: impl Into<String>, value: impl Into<String>) -> Cookie<'static> {
+        let mut cookie = Cookie::new(name.into(), value.into());
+        cookie.set_path(self.path.clone());
+        if let Some(domain) = &self.domain {
+            cookie.set_domain(domain.clone());
+        }
+        cookie.set_same_site(self.same_site);
+        cookie.set_secure(self.secure);
+        cookie.set_http_only(self.http_only);
+        if let Some(max_age) = self
+            .max_age
+            .and_then(|d| tower_cookies::cookie::time::Duration::try_from(d).ok())
+        {
+            cookie.set_max_age(max_age);
+        }
+        cookie
+    }
+
+    /// Create a cookie that removes `name`; path and domain must match the
+    /// original for browsers to delete it
+    pub fn removal(&self, name: impl Into<String>) -> Cookie<'static> {
+        let mut cookie = Cookie::from(name.into());
+        cookie.set_path(self.path.clone());
+        if let Some(domain) = &self.domain {
+            cookie.set_domain(domain.clone());
+        }
+        cookie
+    }
+}
+
+/// Everything a [`StateStore`] needs to read and write a request's state
+pub struct StoreContext<'a> {
+    pub cookies: &'a Cookies,
+    pub attributes: &'a CookieAttributes,
+}
+
+/// App-wide persistence settings, stored as a request extension
+///
+/// Defaults to one plain cookie per field for `Persist::Cookie` and an
+/// in-memory session store for `Persist::Session`, with `SameSite=Lax`
+/// cookies on path `/` shared by all pages.
+#[derive(Clone)]
+pub struct PersistConfig {
+    pub mode: Persist,
+    pub scope: StateScope,
+    pub attributes: CookieAttributes,
+    cookie_store: Arc<dyn StateStore>,
+    session_store: Arc<dyn StateStore>,
+}
+
+impl PersistConfig {
+    /// Create a config with the default stores
+    pub fn new(mode: Persist) -> Self {
+        Self {
+            mode,
+            scope: StateScope::default(),
+            attributes: CookieAttributes::default(),
+            cookie_store: Arc::new(CookieStore),
+            session_store: Arc::new(SessionStore::memory()),
+        }
+    }
+
+    /// Share keys between all pages or namespace them per page
+    pub fn scope(mut self, scope: StateScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Set the `SameSite` attribute (`None` omits it)
+    pub fn same_site(mut self, same_site: impl Into<Option<SameSite>>) -> Self {
+        self.attributes.same_site = same_site.into();
+        self
+    }
+
+    /// Set the `Secure` attribute
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.attributes.secure = secure;
+        self
+    }
+
+    /// Set the `HttpOnly` attribute
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.attributes.http_only = http_only;
+        self
+    }
+
+    /// Set the `Max-Age` attribute; without it cookies last for the browser session
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.attributes.max_age = Some(max_age);
+        self
+    }
+
+    /// Set the `Domain` attribute
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.attributes.domain = Some(domain.into());
+        self
+    }
+
+    /// Set the `Path` attribute (defaults to `/`)
+    pub fn cookie_path(mut self, path: impl Into<String>) -> Self {
+        self.attributes.path = path.into();
+        self
+    }
+
+    /// Set the store used for `Persist::Cookie`
+    pub fn cookie_store(mut self, store: impl StateStore + 'static) -> Self {
+        self.cookie_store = Arc::new(store);
+        self
+    }
+
+    /// Set the store used for `Persist::Session`
+    pub fn session_store(mut self, store: impl StateStore + 'static) -> Self {
+        self.session_store = Arc::new(store);
+        self
+    }
+
+    /// Get the app's config from request extensions, or the default one
+    pub fn from_extensions(extensions: &Extensions) -> &Self {
+        extensions
+            .get::<PersistConfig>()
+            .unwrap_or_else(|| &*DEFAULT_CONFIG)
+    }
+
+    /// Scope name for a request, or `None` when keys are shared by all pages
+    pub fn scope_for(&self, parts: &Parts) -> Option<String> {
+        match self.scope {
+            StateScope::Global => None,
+            StateScope::Page => Some(page_scope(&page_path(parts))),
+        }
+    }
+
+    /// Get the store for a persistence mode, if any
+    pub fn store(&self, mode: Persist) -> Option<Arc<dyn StateStore>> {
+        match mode {
+            Persist::None => None,
+            Persist::Cookie => Some(self.cookie_store.clone()),
+            Persist::Session => Some(self.session_store.clone()),
+        }
+    }
+}
+
+impl Default for PersistConfig {
+    fn default() -> Self {
+        Self::new(Persist::None)
+    }
+}
+
+impl From<Persist> for PersistConfig {
+    fn from(mode: Persist) -> Self {
+        Self::new(mode)
+    }
+}
+
+/// Scope name for a page path: `/projects/list` -> `projects.list`, `/` -> `index`
+pub fn page_scope(page_path: &str) -> String {
+    let trimmed = page_path.trim_matches('/');
+    if trimmed.is_empty() {
+        "index".to_string()
+    } else {
+        trimmed.replace('/', ".")
+    }
+}
+
+/// Stored key for a field, namespaced by page when scoping is enabled
+pub fn scoped_key(scope: Option<&str>, field: &str) -> String {
+    match scope {
+        Some(scope) => format!("{}{}{}", scope, SCOPE_SEPARATOR, field),
+        None => field.to_string(),
+    }
+}
+
+/// Select the stored values belonging to a scope, with the scope removed
+pub(crate) fn unscope(
+    stored: HashMap<String, String>,
+    scope: Option<&str>,
+) -> HashMap<String, String> {
+    let Some(scope) = scope else {
+        return stored;
+    };
+    stored
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let (key_scope, field) = key.split_once(SCOPE_SEPARATOR)?;
+            (key_scope == scope).then(|| (field.to_string(), value))
+        })
+        .collect()
+}
+
+/// Namespace field values for storing under a scope
+pub(crate) fn scope_values(
+    values: HashMap<String, String>,
+    scope: Option<&str>,
+) -> HashMap<String, String> {
+    values
+        .into_iter()
+        .map(|(field, value)| (scoped_key(scope, &field), value))
+        .collect()
+}
+
+/// Path of the page a request belongs to
+///
+/// For htmx requests this is the page that issued the request
+/// (`HX-Current-URL`, with the app's base path removed), otherwise the
+/// request path itself, which the router already sees within the app.
+pub fn page_path(parts: &Parts) -> String {
+    let base = crate::base_path::BasePrefix::from_extensions(&parts.extensions);
+    parts
+        .headers
+        .get("HX-Current-URL")
+        .and_then(|v| v.to_str().ok())
+        .map(|url| {
+            // HX-Current-URL is absolute: strip scheme and host
+            let without_origin = match url.find("://") {
+                Some(idx) => {
+                    let rest = &url[idx + 3..];
+                    rest.find('/').map(|i| &rest[i..]).unwrap_or("/")
+                }
+                None => url,
+            };
+            let path = without_origin.split(['?', '#']).next().unwrap_or("/");
+            crate::base_path::strip(base, path).to_string()
+        })
+        .unwrap_or_else(|| parts.uri.path().to_string())
+}
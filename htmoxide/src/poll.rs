@@ -0,0 +1,211 @@
+//! Polling components
+//!
+//! Progress bars and job status views refresh themselves: the component's
+//! root element re-requests the component every few seconds and is replaced
+//! by the response.
+//!
+//! ```rust,ignore
+//! // Poll every two seconds, doubling the interval while nothing changes,
+//! // up to 30 seconds
+//! #[component(poll = "2s", backoff = "30s")]
+//! async fn job_status(state: JobState, url: UrlBuilder) -> Html {
+//!     let job = load_job(state.id).await;
+//!     let markup = html! { div #job { progress value=(job.done) max="100" {} } };
+//!     if job.finished() {
+//!         // Render the final state and stop polling
+//!         return Html::new(markup).stop_polling();
+//!     }
+//!     markup.into()
+//! }
+//!
+//! // Or decide at runtime
+//! Html::new(markup).poll_every(Duration::from_secs(5))
+//! ```
+//!
+//! The root gets `hx-get` for the requested URL (`/jobs/7/status`, not the
+//! route template) with the current view state, `hx-trigger="every 2s"`, `hx-target="this"` and an `outerHTML`
+//! swap, so it should not carry `hx-*` request attributes of its own.
+//! Stopping responds with htmx's `286` status and leaves the attributes out.
+//!
+//! Streamed responses ([`StreamingPage`](crate::StreamingPage)) are passed
+//! through without polling attributes.
+//!
+//! With backoff, each poll whose markup equals the previous one doubles the
+//! interval, up to the maximum; a change resets it. The previous interval
+//! and a hash of the markup travel in the `_poll` param.
+
+use crate::hx::{self, Swap, Trigger};
+use crate::query::QueryMap;
+use crate::stream::Streamed;
+use crate::url_builder::{ComponentName, UrlBuilder};
+use axum::{
+    body::Body,
+    http::{StatusCode, header, request::Parts},
+    response::Response,
+};
+use std::time::Duration;
+
+/// Query parameter carrying the backoff state of a polling component
+pub const POLL_PARAM: &str = "_poll";
+
+/// Status telling htmx to stop polling
+pub const STOP_POLLING: u16 = 286;
+
+/// How often a component polls
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Poll {
+    interval: Duration,
+    max: Option<Duration>,
+}
+
+impl Poll {
+    /// Poll at a fixed interval
+    pub const fn every(interval: Duration) -> Self {
+        Self {
+            interval,
+            max: None,
+        }
+    }
+
+    /// Double the interval while the markup doesn't change, up to `max`
+    pub const fn backoff(mut self, max: Duration) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    /// Interval after a response, given the previous poll's interval and
+    /// whether the markup changed since
+    fn next(&self, previous: Option<Duration>, changed: bool) -> Duration {
+        match (self.max, previous) {
+            (Some(max), Some(previous)) if !changed => {
+                (previous * 2).clamp(self.interval, max.max(self.interval))
+            }
+            _ => self.interval,
+        }
+    }
+}
+
+/// Polling requested by an [`Html`](crate::Html) response
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Polling {
+    Every(Poll),
+    Stop,
+}
+
+/// Adds polling attributes to a component's response
+///
+/// Created by `#[component]` before calling the component; not meant to be
+/// used directly.
+#[doc(hidden)]
+pub struct Poller {
+    url: UrlBuilder,
+    previous: Option<(Duration, u64)>,
+}
+
+impl Poller {
+    /// `url` is the component's builder, resolved to the requested path
+    pub fn new(parts: &Parts, url: &UrlBuilder) -> Self {
+        let previous = QueryMap::parse(parts.uri.query().unwrap_or(""))
+            .get(POLL_PARAM)
+            .and_then(|value| {
+                let (interval, hash) = value.split_once('.')?;
+                Some((
+                    Duration::from_millis(interval.parse().ok()?),
+                    u64::from_str_radix(hash, 16).ok()?,
+                ))
+            });
+        Self {
+            url: url.clone(),
+            previous,
+        }
+    }
+
+    /// Make the response's root element poll, as the response or the
+    /// component's `poll = "..."` asks
+    pub async fn apply<C: ComponentName>(self, response: Response) -> Response {
+        let poll = match response.extensions().get::<Polling>() {
+            Some(Polling::Every(poll)) => *poll,
+            Some(Polling::Stop) => return response,
+            None => match C::POLL {
+                Some(poll) => poll,
+                None => return response,
+            },
+        };
+        let streamed = response.extensions().get::<Streamed>().is_some();
+        if !response.status().is_success() || streamed {
+            return response;
+        }
+
+        let (mut parts, body) = response.into_parts();
+        let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+            return Response::from_parts(parts, Body::empty());
+        };
+        let html = String::from_utf8_lossy(&bytes);
+        let Some(end) = start_tag_end(&html) else {
+            tracing::warn!("component {} polls but renders no root element", C::name());
+            return Response::from_parts(parts, Body::from(bytes));
+        };
+
+        let hash = markup_hash(&bytes);
+        let changed = self.previous.is_none_or(|(_, previous)| previous != hash);
+        let interval = poll.next(self.previous.map(|(interval, _)| interval), changed);
+
+        let url = match poll.max {
+            Some(_) => self
+                .url
+                .with_params([(POLL_PARAM, format!("{}.{:x}", interval.as_millis(), hash))]),
+            None => self.url,
+        };
+        let hx = hx::get(url)
+            .target("this")
+            .swap(Swap::OuterHtml)
+            .trigger(Trigger::Every(interval))
+            .no_push();
+        let mut attributes = String::new();
+        for (name, value) in hx.attributes() {
+            hx::push_attr(&mut attributes, &name, Some(&value));
+        }
+
+        let mut polling = String::with_capacity(html.len() + attributes.len());
+        polling.push_str(&html[..end]);
+        polling.push_str(&attributes);
+        polling.push_str(&html[end..]);
+        parts.headers.remove(header::CONTENT_LENGTH);
+        Response::from_parts(parts, Body::from(polling))
+    }
+}
+
+/// Status of a response that stops polling
+pub(crate) fn stop_status() -> StatusCode {
+    StatusCode::from_u16(STOP_POLLING).expect("valid status code")
+}
+
+/// Byte offset where attributes can be added to the first start tag
+fn start_tag_end(html: &str) -> Option<usize> {
+    let tag = crate::component::first_element(html)?;
+    let start = tag.as_ptr() as usize - html.as_ptr() as usize;
+    let end = start + tag.trim_end_matches('/').trim_end().len();
+    Some(end)
+}
+
+/// FNV-1a hash of the markup
+///
+/// The hash travels in poll URLs, so it must not change between processes or
+/// Rust releases the way `DefaultHasher` may.
+fn markup_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markup_hash_is_fnv1a() {
+        assert_eq!(markup_hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(markup_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(markup_hash(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+}
@@ -0,0 +1,236 @@
+//! Query string model shared by everything that reads or writes URLs
+//!
+//! [`QueryMap`] keeps every `key=value` pair in order, including repeated
+//! keys, and decodes `+` and `%XX` escapes on parse. Encoding a parsed map
+//! gives back an equivalent query string, so values survive any number of
+//! round trips through `UrlBuilder`:
+//!
+//! ```rust
+//! use htmoxide::QueryMap;
+//!
+//! let query = QueryMap::parse("q=a%20b&tag=x&tag=y");
+//! assert_eq!(query.get("q"), Some("a b"));
+//! assert_eq!(query.get_all("tag").collect::<Vec<_>>(), ["x", "y"]);
+//! assert_eq!(query.to_string(), "q=a+b&tag=x&tag=y");
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// Ordered multimap of decoded query parameters
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueryMap {
+    pairs: Vec<(String, String)>,
+}
+
+impl QueryMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a query string (without the leading `?`)
+    ///
+    /// Pairs with an empty key are dropped; keys without `=` get an empty value.
+    pub fn parse(query: &str) -> Self {
+        let query = query.strip_prefix('?').unwrap_or(query);
+        form_urlencoded::parse(query.as_bytes())
+            .filter(|(key, _)| !key.is_empty())
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect()
+    }
+
+    /// First value of a key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// All values of a key, in order
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.pairs
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.pairs.iter().any(|(k, _)| k == key)
+    }
+
+    /// Set a key to a single value, keeping the position of its first occurrence
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        let key = key.into();
+        let value = value.into();
+        match self.pairs.iter().position(|(k, _)| *k == key) {
+            Some(index) => {
+                self.pairs[index].1 = value;
+                let mut seen = false;
+                self.pairs.retain(|(k, _)| {
+                    let duplicate = *k == key && seen;
+                    seen |= *k == key;
+                    !duplicate
+                });
+            }
+            None => self.pairs.push((key, value)),
+        }
+    }
+
+    /// Add a value for a key, keeping existing ones
+    pub fn append(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.pairs.push((key.into(), value.into()));
+    }
+
+    /// Set all values of a key, keeping the position of its first occurrence
+    pub fn set_all<I, V>(&mut self, key: impl Into<String>, values: I)
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<String>,
+    {
+        let key = key.into();
+        let position = self.pairs.iter().position(|(k, _)| *k == key);
+        self.pairs.retain(|(k, _)| *k != key);
+        let index = position.unwrap_or(self.pairs.len()).min(self.pairs.len());
+        let new_pairs: Vec<_> = values
+            .into_iter()
+            .map(|value| (key.clone(), value.into()))
+            .collect();
+        self.pairs.splice(index..index, new_pairs);
+    }
+
+    /// Remove every value of a key
+    pub fn remove(&mut self, key: &str) {
+        self.pairs.retain(|(k, _)| k != key);
+    }
+
+    /// Distinct keys in order of first occurrence
+    pub fn keys(&self) -> Vec<&str> {
+        let mut keys: Vec<&str> = Vec::new();
+        for (key, _) in &self.pairs {
+            if !keys.contains(&key.as_str()) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn retain(&mut self, mut f: impl FnMut(&str, &str) -> bool) {
+        self.pairs.retain(|(k, v)| f(k, v));
+    }
+
+    /// Sort pairs by key, keeping the order of repeated values
+    pub fn sort_by_key<K: Ord>(&mut self, mut f: impl FnMut(&str) -> K) {
+        self.pairs.sort_by_key(|(k, _)| f(k));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// First value of every key
+    pub fn to_map(&self) -> HashMap<String, String> {
+        let mut map = HashMap::new();
+        for (key, value) in &self.pairs {
+            map.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+        map
+    }
+}
+
+impl fmt::Display for QueryMap {
+    /// Encode as `application/x-www-form-urlencoded`, the format htmx and
+    /// browsers use for forms
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let encoded = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(&self.pairs)
+            .finish();
+        f.write_str(&encoded)
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for QueryMap {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self {
+            pairs: iter
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        }
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> Extend<(K, V)> for QueryMap {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        self.pairs
+            .extend(iter.into_iter().map(|(k, v)| (k.into(), v.into())));
+    }
+}
+
+impl<'a> IntoIterator for &'a QueryMap {
+    type Item = (&'a str, &'a str);
+    type IntoIter = std::iter::Map<
+        std::slice::Iter<'a, (String, String)>,
+        fn(&'a (String, String)) -> (&'a str, &'a str),
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pairs.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+impl IntoIterator for QueryMap {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pairs.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_decodes_escapes() {
+        let query = QueryMap::parse("?q=a+b%26c&path=%2Ftodos%3Fx%3D1&emoji=%F0%9F%A6%80&flag");
+
+        assert_eq!(query.get("q"), Some("a b&c"));
+        assert_eq!(query.get("path"), Some("/todos?x=1"));
+        assert_eq!(query.get("emoji"), Some("🦀"));
+        assert_eq!(query.get("flag"), Some(""));
+    }
+
+    #[test]
+    fn encoding_round_trips() {
+        let original = "q=a+b%26c&tag=x&tag=y&empty=&note=100%25";
+        let query = QueryMap::parse(original);
+
+        assert_eq!(query.to_string(), original);
+        assert_eq!(QueryMap::parse(&query.to_string()), query);
+    }
+
+    #[test]
+    fn repeated_keys_keep_their_order() {
+        let mut query = QueryMap::parse("tag=x&page=2&tag=y");
+
+        assert_eq!(query.get_all("tag").collect::<Vec<_>>(), ["x", "y"]);
+        assert_eq!(query.keys(), ["tag", "page"]);
+        assert_eq!(query.to_map()["tag"], "x");
+
+        query.set_all("tag", ["z"]);
+        assert_eq!(query.to_string(), "tag=z&page=2");
+        query.insert("page", "3");
+        query.append("tag", "w");
+        assert_eq!(query.to_string(), "tag=z&page=3&tag=w");
+    }
+}
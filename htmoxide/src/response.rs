@@ -1,22 +1,23 @@
+use crate::poll::{Poll, Polling};
 use axum::{
     http::{HeaderValue, StatusCode},
     response::{IntoResponse, Response},
 };
 use maud::{Markup, Render};
+use std::time::Duration;
 
 /// Response type for component partial renders
 #[derive(Debug, Clone)]
 pub struct Html {
     pub markup: Markup,
     pub push_url: Option<String>,
+    /// Polling of the component's root, see `htmoxide::poll`
+    pub poll: Option<Polling>,
 }
 
 impl From<Markup> for Html {
     fn from(markup: Markup) -> Self {
-        Html {
-            markup,
-            push_url: None,
-        }
+        Html::new(markup)
     }
 }
 
@@ -25,6 +26,7 @@ impl Html {
         Html {
             markup,
             push_url: None,
+            poll: None,
         }
     }
 
@@ -32,6 +34,23 @@ impl Html {
         self.push_url = Some(url);
         self
     }
+
+    /// Re-request the component every `interval`, overriding `poll = "..."`
+    pub fn poll_every(self, interval: Duration) -> Self {
+        self.poll(Poll::every(interval))
+    }
+
+    /// Poll on a schedule, e.g. `Poll::every(secs(2)).backoff(secs(30))`
+    pub fn poll(mut self, poll: Poll) -> Self {
+        self.poll = Some(Polling::Every(poll));
+        self
+    }
+
+    /// Respond with status 286, which makes htmx stop polling
+    pub fn stop_polling(mut self) -> Self {
+        self.poll = Some(Polling::Stop);
+        self
+    }
 }
 
 impl Render for Html {
@@ -42,8 +61,12 @@ impl Render for Html {
 
 impl IntoResponse for Html {
     fn into_response(self) -> Response {
+        let status = match self.poll {
+            Some(Polling::Stop) => crate::poll::stop_status(),
+            _ => StatusCode::OK,
+        };
         let mut response = (
-            StatusCode::OK,
+            status,
             [("Content-Type", "text/html; charset=utf-8")],
             self.markup.into_string(),
         )
@@ -56,6 +79,11 @@ impl IntoResponse for Html {
             response.headers_mut().insert("HX-Push-Url", header_value);
         }
 
+        // Applied by the component handler, which knows the component's URL
+        if let Some(poll) = self.poll {
+            response.extensions_mut().insert(poll);
+        }
+
         response
     }
 }
@@ -0,0 +1,361 @@
+//! Tamper-evident links
+//!
+//! A signed URL carries an HMAC over some of its params, so the recipient
+//! can read them but not change them. Signed params stay in the URL as usual;
+//! the signature travels in three extra params:
+//!
+//! - `_signed`: the signed param names
+//! - `_exp`: expiry as a Unix timestamp, if any
+//! - `_sig`: the base64url HMAC-SHA256
+//!
+//! ```rust,ignore
+//! use htmoxide::{Signed, tower_cookies::Key};
+//!
+//! let key = Key::generate();
+//! let app = htmoxide::app().htmx().signing_key(key.clone());
+//!
+//! // Build an invitation valid for a week
+//! let link = url
+//!     .for_page(InvitePage)
+//!     .with_state(&InviteState { team: 7, role: "viewer".into() })
+//!     .signed(&key, Duration::from_secs(7 * 24 * 60 * 60))
+//!     .absolute()
+//!     .build();
+//!
+//! // Rejects links whose state was modified or that expired
+//! #[page("/invite")]
+//! async fn invite_page(Signed(invite): Signed<InviteState>) -> Page { /* ... */ }
+//! ```
+//!
+//! The signature covers the path within the app (without the base path), so
+//! a link can't be replayed against another route. The state is decoded
+//! with the same codec as component hydration. Every field of the state must
+//! be signed; other params in the URL are ignored.
+
+use crate::query::QueryMap;
+use crate::state::FIELDS_PARAM;
+use axum::{
+    extract::FromRequestParts,
+    http::{StatusCode, request::Parts},
+};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use serde::de::DeserializeOwned;
+use sha2::Sha256;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tower_cookies::Key;
+
+/// Query parameter listing the signed params
+pub const SIGNED_PARAM: &str = "_signed";
+
+/// Query parameter holding the expiry timestamp
+pub const EXPIRES_PARAM: &str = "_exp";
+
+/// Query parameter holding the signature
+pub const SIGNATURE_PARAM: &str = "_sig";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Key used by the [`Signed`] extractor, added with `HtmxRouterExt::signing_key`
+#[derive(Clone)]
+pub struct SigningKey(pub Key);
+
+/// How `UrlBuilder` signs a URL
+#[derive(Clone)]
+pub(crate) struct Signing {
+    pub key: Key,
+    /// Params to sign, or every param in the URL
+    pub fields: Option<Vec<String>>,
+    pub expires_at: Option<u64>,
+}
+
+impl Signing {
+    pub fn new(key: &Key, fields: Option<Vec<String>>, expires_in: Option<Duration>) -> Self {
+        Self {
+            key: key.clone(),
+            fields,
+            expires_at: expires_in.map(|d| unix_now().saturating_add(d.as_secs())),
+        }
+    }
+
+    /// Replace any signature in `params` with one over `path` (within the
+    /// app) and the current values
+    ///
+    /// Signing every param also covers the `implied` keys, so view state
+    /// fields left out as defaults can't be added by the recipient.
+    pub fn sign(&self, path: &str, params: &mut QueryMap, implied: &[String]) {
+        remove_signature(params);
+        let fields: Vec<String> = match &self.fields {
+            Some(fields) => fields.clone(),
+            None => {
+                let mut fields: Vec<String> = implied.to_vec();
+                for key in params.keys() {
+                    if key != FIELDS_PARAM && !fields.iter().any(|f| f == key) {
+                        fields.push(key.to_string());
+                    }
+                }
+                fields
+            }
+        };
+        if fields.is_empty() {
+            return;
+        }
+        let fields = fields.join(",");
+        let expires = self.expires_at.map(|t| t.to_string());
+        let payload = payload(path, params, &fields, expires.as_deref());
+
+        params.append(SIGNED_PARAM, fields);
+        if let Some(expires) = expires {
+            params.append(EXPIRES_PARAM, expires);
+        }
+        params.append(SIGNATURE_PARAM, mac(&self.key, &payload));
+    }
+}
+
+/// Drop signature params, e.g. before re-signing
+pub(crate) fn remove_signature(params: &mut QueryMap) {
+    params.remove(SIGNED_PARAM);
+    params.remove(EXPIRES_PARAM);
+    params.remove(SIGNATURE_PARAM);
+}
+
+/// Why a signed link was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureError {
+    /// The app has no `signing_key()`
+    MissingKey,
+    /// The URL carries no signature
+    Unsigned,
+    /// A param was changed, or the signature is not ours
+    Invalid,
+    /// The link is past its expiry
+    Expired,
+    /// A field of the state is not covered by the signature
+    Incomplete,
+}
+
+impl std::fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SignatureError::MissingKey => "no signing key configured",
+            SignatureError::Unsigned => "link is not signed",
+            SignatureError::Invalid => "link signature is invalid",
+            SignatureError::Expired => "link has expired",
+            SignatureError::Incomplete => "link does not sign every state field",
+        })
+    }
+}
+
+impl std::error::Error for SignatureError {}
+
+/// Verify the query string of a request to `path` (within the app, without
+/// the base path) and return the signed params
+pub fn verify(key: &Key, path: &str, query: &str) -> Result<QueryMap, SignatureError> {
+    verify_fields(key, path, query).map(|(_, signed)| signed)
+}
+
+/// Verify a query string, returning the signed field names and params
+fn verify_fields(
+    key: &Key,
+    path: &str,
+    query: &str,
+) -> Result<(Vec<String>, QueryMap), SignatureError> {
+    let params = QueryMap::parse(query);
+    let (Some(fields), Some(signature)) = (params.get(SIGNED_PARAM), params.get(SIGNATURE_PARAM))
+    else {
+        return Err(SignatureError::Unsigned);
+    };
+    let expires = params.get(EXPIRES_PARAM);
+
+    let expected = URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|_| SignatureError::Invalid)?;
+    let mut mac = HmacSha256::new_from_slice(key.signing()).expect("HMAC accepts any key length");
+    mac.update(payload(path, &params, fields, expires).as_bytes());
+    mac.verify_slice(&expected)
+        .map_err(|_| SignatureError::Invalid)?;
+
+    // Checked after the MAC so a forged expiry is reported as invalid
+    if let Some(expires) = expires {
+        let expires: u64 = expires.parse().map_err(|_| SignatureError::Invalid)?;
+        if unix_now() > expires {
+            return Err(SignatureError::Expired);
+        }
+    }
+
+    let fields: Vec<String> = fields.split(',').map(String::from).collect();
+    let mut signed = params.clone();
+    signed.retain(|key, _| fields.iter().any(|f| f == key));
+    Ok((fields, signed))
+}
+
+/// View state read from a signed URL
+///
+/// Rejects the request with `403 Forbidden` if the link was modified, has
+/// expired or leaves a field of `T` unsigned.
+#[derive(Debug, Clone)]
+pub struct Signed<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for Signed<T>
+where
+    T: DeserializeOwned + Default + 'static,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let reject = |e: SignatureError| {
+            let status = match e {
+                SignatureError::MissingKey => StatusCode::INTERNAL_SERVER_ERROR,
+                _ => StatusCode::FORBIDDEN,
+            };
+            (status, e.to_string())
+        };
+
+        let key = parts
+            .extensions
+            .get::<SigningKey>()
+            .ok_or(reject(SignatureError::MissingKey))?;
+        // Routers see the path within the app, as the link was signed
+        let (fields, signed) =
+            verify_fields(&key.0, parts.uri.path(), parts.uri.query().unwrap_or(""))
+                .map_err(reject)?;
+
+        // Unsigned fields could be set freely by the recipient; a signed
+        // compact param covers the whole state
+        let packed = crate::view_state::view_state_info::<T>()
+            .and_then(|info| info.compact)
+            .is_some_and(|compact| fields.iter().any(|f| f == compact.param));
+        let keys = crate::state::state_keys::<T>();
+        if !packed && keys.iter().any(|k| !fields.contains(k)) {
+            return Err(reject(SignatureError::Incomplete));
+        }
+
+        Ok(Signed(crate::state::state_from_query(&signed.to_string())))
+    }
+}
+
+impl<T> std::ops::Deref for Signed<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// The signed message: path, field list, expiry and the signed params in
+/// order
+///
+/// Fields missing from the URL (e.g. left out as defaults) sign as absent.
+fn payload(path: &str, params: &QueryMap, fields: &str, expires: Option<&str>) -> String {
+    let mut message = QueryMap::new();
+    message.append("_path", path);
+    message.append(SIGNED_PARAM, fields);
+    message.append(EXPIRES_PARAM, expires.unwrap_or(""));
+    for field in fields.split(',') {
+        for value in params.get_all(field) {
+            message.append(field, value);
+        }
+    }
+    message.to_string()
+}
+
+fn mac(key: &Key, payload: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(key.signing()).expect("HMAC accepts any key length");
+    mac.update(payload.as_bytes());
+    URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed(signing: &Signing, path: &str, query: &str) -> String {
+        let mut params = QueryMap::parse(query);
+        signing.sign(path, &mut params, &[]);
+        params.to_string()
+    }
+
+    #[test]
+    fn verifies_untouched_links() {
+        let key = Key::generate();
+        let query = signed(
+            &Signing::new(&key, None, None),
+            "/invite",
+            "team=7&role=viewer",
+        );
+
+        let params = verify(&key, "/invite", &query).unwrap();
+        assert_eq!(params.get("team"), Some("7"));
+        assert_eq!(params.get("role"), Some("viewer"));
+    }
+
+    #[test]
+    fn rejects_tampered_params() {
+        let key = Key::generate();
+        let query = signed(
+            &Signing::new(&key, None, None),
+            "/invite",
+            "team=7&role=viewer",
+        );
+        let tampered = query.replace("role=viewer", "role=admin");
+
+        assert_eq!(
+            verify(&key, "/invite", &tampered).unwrap_err(),
+            SignatureError::Invalid
+        );
+        assert_eq!(
+            verify(&Key::generate(), "/invite", &query).unwrap_err(),
+            SignatureError::Invalid
+        );
+        assert_eq!(
+            verify(&key, "/invite", "team=7").unwrap_err(),
+            SignatureError::Unsigned
+        );
+    }
+
+    #[test]
+    fn rejects_expired_links() {
+        let key = Key::generate();
+        let mut signing = Signing::new(&key, None, Some(Duration::from_secs(60)));
+        let query = signed(&signing, "/invite", "team=7");
+        assert!(verify(&key, "/invite", &query).is_ok());
+
+        let expired = unix_now() - 1;
+        signing.expires_at = Some(expired);
+        let query = signed(&signing, "/invite", "team=7");
+        assert_eq!(
+            verify(&key, "/invite", &query).unwrap_err(),
+            SignatureError::Expired
+        );
+
+        // Extending the expiry breaks the signature
+        let extended = query.replace(
+            &format!("{}={}", EXPIRES_PARAM, expired),
+            &format!("{}={}", EXPIRES_PARAM, expired + 60),
+        );
+        assert_eq!(
+            verify(&key, "/invite", &extended).unwrap_err(),
+            SignatureError::Invalid
+        );
+    }
+
+    #[test]
+    fn rejects_links_replayed_on_another_path() {
+        let key = Key::generate();
+        let query = signed(&Signing::new(&key, None, None), "/invite", "team=7");
+
+        assert_eq!(
+            verify(&key, "/admin/grant", &query).unwrap_err(),
+            SignatureError::Invalid
+        );
+    }
+}
@@ -1,27 +1,90 @@
-use axum::{
-    extract::{FromRequestParts, Query},
-    http::request::Parts,
-};
-use serde::de::DeserializeOwned;
+use crate::persist::{Persist, PersistConfig, StoreContext, scope_values, unscope};
+use crate::query::QueryMap;
+use crate::view_state::{migrate_query_for, migrate_request, view_state_info};
+use axum::{extract::FromRequestParts, http::request::Parts};
+use serde::{Serialize, de::DeserializeOwned};
+use std::collections::{HashMap, HashSet};
+use tower_cookies::Cookies;
+
+/// Query parameter listing the state fields a request is expected to carry
+///
+/// Browsers omit unchecked checkboxes, and `UrlBuilder` drops empty values.
+/// Fields named in this comma-separated manifest but missing from the request
+/// are reset to their default instead of being restored from the persisted
+/// store. See `state_fields()` and `UrlBuilder::with_params`.
+pub const FIELDS_PARAM: &str = "_fields";
 
 /// Extractor for component state from query parameters
+///
+/// Outdated params are upgraded first when the type is registered with
+/// `#[view_state]`. Params that fail to deserialize (e.g. after a field type
+/// changed) fall back to their default individually instead of resetting
+/// the whole state.
 #[derive(Debug, Clone)]
 pub struct StateExtractor<T>(pub T);
 
 impl<T, S> FromRequestParts<S> for StateExtractor<T>
 where
-    T: DeserializeOwned + Default,
+    T: DeserializeOwned + Default + 'static,
     S: Send + Sync,
 {
     type Rejection = std::convert::Infallible;
 
-    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        // Try to extract from query params
-        match Query::<T>::from_request_parts(parts, state).await {
-            Ok(Query(value)) => Ok(StateExtractor(value)),
-            Err(_) => Ok(StateExtractor(T::default())),
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let query = parts.uri.query().unwrap_or("");
+        Ok(StateExtractor(state_from_query(query)))
+    }
+}
+
+/// Read a view state from a query string the way requests are hydrated
+pub(crate) fn state_from_query<T: DeserializeOwned + Default + 'static>(query: &str) -> T {
+    let query = &*crate::compact::expand_query(query);
+    match migrate_query_for::<T>(query) {
+        Some(canonical) => deserialize_params(&canonical),
+        None => deserialize_params(query),
+    }
+}
+
+/// Encode a view state with the codec `StateExtractor` reads it with
+///
+/// Every key of the state is included; keys the encoding leaves out (such
+/// as `None` options) get an empty value.
+pub(crate) fn encode_state<T>(state: &T) -> QueryMap
+where
+    T: DeserializeOwned + Serialize + 'static,
+{
+    let encoded: QueryMap = match serde_urlencoded::to_string(state) {
+        Ok(query) => QueryMap::parse(&query),
+        // Nested values can't be encoded, keep the scalar fields
+        Err(_) => scalar_fields(state).into_iter().collect(),
+    };
+    let mut params = QueryMap::new();
+    for key in state_keys::<T>() {
+        if encoded.contains_key(&key) {
+            params.extend(encoded.get_all(&key).map(|value| (key.as_str(), value)));
+        } else if view_state_info::<T>().is_none_or(|info| info.version_param != key) {
+            params.append(key, "");
+        }
+    }
+    params
+}
+
+/// Deserialize a state from a query string, skipping params that don't fit
+fn deserialize_params<T: DeserializeOwned + Default>(query: &str) -> T {
+    if let Ok(state) = serde_urlencoded::from_str(query) {
+        return state;
+    }
+
+    let mut accepted = QueryMap::new();
+    for (key, value) in QueryMap::parse(query) {
+        let mut candidate = accepted.clone();
+        candidate.append(key, value);
+        if serde_urlencoded::from_str::<T>(&candidate.to_string()).is_ok() {
+            accepted = candidate;
         }
     }
+
+    serde_urlencoded::from_str(&accepted.to_string()).unwrap_or_default()
 }
 
 impl<T> std::ops::Deref for StateExtractor<T> {
@@ -37,3 +100,208 @@ impl<T> std::ops::DerefMut for StateExtractor<T> {
         &mut self.0
     }
 }
+
+/// Hydrate a component's view state from the request
+///
+/// Query parameters are always used. When persistence is enabled (on the app
+/// via `persist_state()` or on the component via `#[component(persist = ...)]`),
+/// fields still at their default value are filled from the persisted store and
+/// the resulting state is written back to it.
+///
+/// Fields the request carries explicitly (even as empty values) or lists in
+/// its [`FIELDS_PARAM`] manifest are never restored from the store, so clearing
+/// an input also clears its persisted value.
+///
+/// Packed states (see [`crate::compact`]) are unpacked into the request URI
+/// first. For types registered with `#[view_state]`, outdated params are upgraded
+/// and the request URI is rewritten to the canonical query; a
+/// `StateMigration` is left in the request extensions for the caller to
+/// redirect or send `HX-Replace-Url`. Persisted values are upgraded too.
+///
+/// This is what the `#[component]` macro calls for the first parameter.
+pub async fn hydrate<T>(parts: &mut Parts, component_persist: Option<Persist>) -> T
+where
+    T: DeserializeOwned + Serialize + Default + 'static,
+{
+    crate::compact::expand_request(parts);
+    migrate_request::<T>(parts);
+    let Ok(StateExtractor(mut state)) = StateExtractor::<T>::from_request_parts(parts, &()).await;
+
+    // Component setting wins over the app-wide setting
+    let config = PersistConfig::from_extensions(&parts.extensions).clone();
+    let Some(store) = config.store(component_persist.unwrap_or(config.mode)) else {
+        return state;
+    };
+    let scope = config.scope_for(parts);
+
+    // Persistence needs the cookie layer (`.htmx()`), skip silently without it
+    let Ok(cookies) = Cookies::from_request_parts(parts, &()).await else {
+        return state;
+    };
+    let ctx = StoreContext {
+        cookies: &cookies,
+        attributes: &config.attributes,
+    };
+
+    let mut stored = unscope(store.load(&ctx).await, scope.as_deref());
+    let mut values = HashMap::new();
+    if let Some(info) = view_state_info::<T>() {
+        // Drop values stored under a field's old name once upgraded
+        for (old, _) in info.aliases {
+            if stored.contains_key(*old) {
+                values.insert(old.to_string(), String::new());
+            }
+        }
+        info.upgrade(&mut stored);
+        if info.version > 1 {
+            values.insert(info.version_param.to_string(), info.version.to_string());
+        }
+    }
+
+    let expected = expected_fields(parts.uri.query().unwrap_or(""));
+    state = merge_stored(state, &stored, &expected);
+    values.extend(scalar_fields(&state));
+    store
+        .save(&ctx, &scope_values(values, scope.as_deref()))
+        .await;
+
+    state
+}
+
+/// Fields whose value is decided by the request itself: every key present in
+/// the query string plus every field named in its manifest
+pub(crate) fn expected_fields(query: &str) -> HashSet<String> {
+    let mut fields = HashSet::new();
+    for (key, value) in &QueryMap::parse(query) {
+        if key == FIELDS_PARAM {
+            fields.extend(manifest_fields(value));
+        } else {
+            fields.insert(key.to_string());
+        }
+    }
+    fields
+}
+
+/// Field names listed in a manifest value
+pub(crate) fn manifest_fields(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(String::from)
+}
+
+/// Query keys a view state reads: its fields in declaration order plus its
+/// version param
+///
+/// The `#[component]` macro registers this for each component so the
+/// state URLs middleware knows which keys belong to a page. See
+/// [`crate::introspect`] for how fields are found.
+pub fn state_keys<T>() -> Vec<String>
+where
+    T: DeserializeOwned + 'static,
+{
+    let mut keys: Vec<String> = crate::introspect::field_names::<T>()
+        .unwrap_or_default()
+        .iter()
+        .map(|field| field.to_string())
+        .collect();
+    if let Some(info) = view_state_info::<T>()
+        && info.version > 1
+    {
+        keys.push(info.version_param.to_string());
+    }
+    keys
+}
+
+/// Default values of a view state's scalar fields, by query key
+///
+/// Registered by the `#[component]` macro next to [`state_keys`], so URLs
+/// built for a component leave out its defaults.
+pub fn state_defaults<T>() -> HashMap<String, String>
+where
+    T: Serialize + Default,
+{
+    scalar_fields(&T::default())
+}
+
+/// Whether a query key belongs to view state `T`: one of its fields, an old
+/// field name from `#[view_state(alias)]`, its version param or its compact
+/// param
+pub(crate) fn is_state_key<T: DeserializeOwned + 'static>(key: &str) -> bool {
+    let is_field = crate::introspect::field_names::<T>()
+        .unwrap_or_default()
+        .contains(&key);
+    is_field
+        || view_state_info::<T>().is_some_and(|info| {
+            info.version_param == key
+                || info.aliases.iter().any(|(old, _)| *old == key)
+                || info.compact.is_some_and(|compact| compact.param == key)
+        })
+}
+
+/// Fill fields that are still at their default value from stored values,
+/// leaving fields the request decided on untouched
+fn merge_stored<T>(state: T, stored: &HashMap<String, String>, expected: &HashSet<String>) -> T
+where
+    T: DeserializeOwned + Serialize + Default,
+{
+    let (Ok(default_json), Ok(mut state_json)) = (
+        serde_json::to_value(T::default()),
+        serde_json::to_value(&state),
+    ) else {
+        return state;
+    };
+    let (Some(default_obj), Some(state_obj)) =
+        (default_json.as_object(), state_json.as_object_mut())
+    else {
+        return state;
+    };
+
+    for (key, default_value) in default_obj {
+        if expected.contains(key) || state_obj.get(key) != Some(default_value) {
+            continue;
+        }
+        if let Some(stored_value) = stored.get(key).filter(|v| !v.is_empty())
+            && let Some(parsed) = parse_value(stored_value)
+        {
+            state_obj.insert(key.clone(), parsed);
+        }
+    }
+
+    serde_json::from_value(state_json).unwrap_or(state)
+}
+
+/// Flatten the scalar fields of a state into strings for persistence
+pub(crate) fn scalar_fields<T: Serialize>(state: &T) -> HashMap<String, String> {
+    let Ok(serde_json::Value::Object(obj)) = serde_json::to_value(state) else {
+        return HashMap::new();
+    };
+
+    obj.into_iter()
+        .filter_map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::String(s) => s,
+                serde_json::Value::Number(n) => n.to_string(),
+                serde_json::Value::Bool(b) => b.to_string(),
+                _ => return None,
+            };
+            Some((key, value))
+        })
+        .collect()
+}
+
+/// Parse a string value into a JSON value
+pub(crate) fn parse_value(value: &str) -> Option<serde_json::Value> {
+    if let Ok(num) = value.parse::<i64>() {
+        Some(serde_json::Value::Number(num.into()))
+    } else if let Ok(num) = value.parse::<f64>() {
+        serde_json::Number::from_f64(num).map(serde_json::Value::Number)
+    } else if let Ok(b) = value.parse::<bool>() {
+        Some(serde_json::Value::Bool(b))
+    } else {
+        // Always return a string, even if empty
+        // Empty strings are valid values that should override cookies
+        Some(serde_json::Value::String(value.to_string()))
+    }
+}
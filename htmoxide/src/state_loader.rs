@@ -1,5 +1,15 @@
+use crate::persist::{CookieAttributes, Persist, PersistConfig, StoreContext, unscope};
+use crate::query::QueryMap;
+use crate::state::{FIELDS_PARAM, manifest_fields, parse_value};
+use crate::store::{CookieStore, StateStore};
+use crate::view_state::view_state_info;
+use axum::{
+    extract::FromRequestParts,
+    http::{StatusCode, request::Parts},
+};
 use serde::de::DeserializeOwned;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use tower_cookies::Cookies;
 
 /// Helper for loading component state from cookies and URL parameters
@@ -8,9 +18,15 @@ use tower_cookies::Cookies;
 /// 1. Load default state
 /// 2. Override with values from cookies
 /// 3. Override with values from URL query params (highest priority)
+///
+/// When used as an extractor, persisted values come from the app's configured
+/// `StateStore` (plain cookies if persistence is disabled).
 pub struct StateLoader {
     cookies: Cookies,
     query_params: HashMap<String, String>,
+    store: Arc<dyn StateStore>,
+    attributes: CookieAttributes,
+    scope: Option<String>,
 }
 
 impl StateLoader {
@@ -19,19 +35,51 @@ impl StateLoader {
         Self {
             cookies,
             query_params,
+            store: Arc::new(CookieStore),
+            attributes: CookieAttributes::default(),
+            scope: None,
         }
     }
 
+    /// Load persisted values from a different store
+    pub fn with_store(mut self, store: Arc<dyn StateStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Only read persisted values stored under a page scope
+    pub fn with_scope(mut self, scope: Option<String>) -> Self {
+        self.scope = scope;
+        self
+    }
+
     /// Load state with cookie fallback and URL override
     ///
     /// Priority order (highest to lowest):
     /// 1. URL query parameters (bookmarkable)
-    /// 2. Cookies (persistence)
+    /// 2. Persisted values (cookies or session)
     /// 3. Default values
-    pub fn load<T>(&self) -> T
+    pub async fn load<T>(&self) -> T
     where
-        T: DeserializeOwned + Default + serde::Serialize,
+        T: DeserializeOwned + Default + serde::Serialize + 'static,
     {
+        let ctx = StoreContext {
+            cookies: &self.cookies,
+            attributes: &self.attributes,
+        };
+        let mut stored = unscope(self.store.load(&ctx).await, self.scope.as_deref());
+        let mut query_params = self.query_params.clone();
+
+        // Upgrade outdated `#[view_state]` params from old links and cookies
+        if let Some(info) = view_state_info::<T>() {
+            info.upgrade(&mut stored);
+            info.upgrade(&mut query_params);
+        }
+        let manifest: HashSet<String> = query_params
+            .get(FIELDS_PARAM)
+            .map(|fields| manifest_fields(fields).collect())
+            .unwrap_or_default();
+
         // Start with default state
         let mut state = T::default();
 
@@ -46,17 +94,18 @@ impl StateLoader {
                 for (key, default_value) in default_obj {
                     let mut current_value = default_value.clone();
 
-                    // First, try to load from cookie
-                    if let Some(cookie) = self.cookies.get(key) {
-                        let cookie_value = cookie.value();
-                        if let Some(parsed) = Self::parse_value(cookie_value) {
-                            current_value = parsed;
-                        }
+                    // First, try to load from the store, unless the request
+                    // declared the field and omitted it (cleared input)
+                    if !manifest.contains(key)
+                        && let Some(stored_value) = stored.get(key)
+                        && let Some(parsed) = parse_value(stored_value)
+                    {
+                        current_value = parsed;
                     }
 
                     // Then, override with query param if present
-                    if let Some(query_value) = self.query_params.get(key)
-                        && let Some(parsed) = Self::parse_value(query_value)
+                    if let Some(query_value) = query_params.get(key)
+                        && let Some(parsed) = parse_value(query_value)
                     {
                         current_value = parsed;
                     }
@@ -73,19 +122,29 @@ impl StateLoader {
 
         state
     }
+}
 
-    /// Parse a string value into a JSON value
-    fn parse_value(value: &str) -> Option<serde_json::Value> {
-        if let Ok(num) = value.parse::<i64>() {
-            Some(serde_json::Value::Number(num.into()))
-        } else if let Ok(num) = value.parse::<f64>() {
-            serde_json::Number::from_f64(num).map(serde_json::Value::Number)
-        } else if let Ok(b) = value.parse::<bool>() {
-            Some(serde_json::Value::Bool(b))
-        } else {
-            // Always return a string, even if empty
-            // Empty strings are valid values that should override cookies
-            Some(serde_json::Value::String(value.to_string()))
-        }
+impl<S> FromRequestParts<S> for StateLoader
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let cookies = Cookies::from_request_parts(parts, state).await?;
+        // Decoded like every other query reader, first value of repeated keys
+        let query_params = QueryMap::parse(parts.uri.query().unwrap_or("")).to_map();
+
+        let config = PersistConfig::from_extensions(&parts.extensions);
+        let store = config
+            .store(config.mode)
+            .or_else(|| config.store(Persist::Cookie))
+            .expect("cookie store is always configured");
+
+        let mut loader = Self::new(cookies, query_params)
+            .with_store(store)
+            .with_scope(config.scope_for(parts));
+        loader.attributes = config.attributes.clone();
+        Ok(loader)
     }
 }
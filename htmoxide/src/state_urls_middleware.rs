@@ -1,18 +1,84 @@
+use crate::base_path::BasePrefix;
+use crate::persist::{SCOPE_SEPARATOR, page_scope};
+use crate::query::QueryMap;
+use crate::store::{SESSION_COOKIE, STATE_COOKIE};
+use crate::url_builder::ComponentName;
 use axum::{
+    body::{Body, HttpBody},
     extract::Request,
+    http::{HeaderValue, Method, StatusCode, Uri, header},
     middleware::Next,
     response::{IntoResponse, Redirect, Response},
 };
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
 use std::sync::Arc;
 use tower_cookies::Cookies;
 
 /// Configuration for state URLs middleware
+///
+/// By default every cookie that is not denylisted is copied into the URL.
+/// In allowlist mode only the keys of the view states mounted on the
+/// requested page are, so unrelated cookies (analytics, consent, third-party
+/// values) never end up in shared links:
+///
+/// ```rust,ignore
+/// let config = StateUrlsConfig::default()
+///     .mount::<TodoList>("/")
+///     .mount::<ProjectTable>("/projects/{id}");
+/// ```
+///
+/// Besides exact names, whole cookie families can be excluded with
+/// [`CookieRule`]s, and [`StateUrlsConfig::explain`] tells why a cookie was
+/// or wasn't promoted:
+///
+/// ```rust,ignore
+/// let config = StateUrlsConfig::default()
+///     .deny_glob("_ga*")
+///     .deny_suffix("_token")
+///     .max_value_len(256)
+///     .rule(CookieRule::regex(r"(?i)^csrf")?);
+///
+/// println!("{}", config.explain("_ga_XYZ", "GS1.1", "/"));
+/// ```
+///
+/// With [`StateUrlsMode::ClientSync`] the page renders directly from the
+/// cookie state and the browser URL is updated client-side, saving the
+/// redirect round-trip. Streamed responses, whose length isn't known up
+/// front, are passed through without the script and keep the requested URL:
+///
+/// ```rust,ignore
+/// let config = StateUrlsConfig::default()
+///     .mode(StateUrlsMode::ClientSync)
+///     .html_only(true);
+/// ```
 #[derive(Clone, Debug)]
 pub struct StateUrlsConfig {
     /// Cookie names to exclude from being added to query params
     /// Common examples: "token", "session_id", "csrf_token", "auth"
     pub denylist: Arc<HashSet<String>>,
+    /// Name and value based rules excluding cookies
+    pub rules: Arc<Vec<CookieRule>>,
+    /// Only promote keys allowed for the requested page
+    pub allowlist_only: bool,
+    /// Page route patterns and the state keys allowed on them
+    pub pages: Arc<Vec<(String, HashSet<String>)>>,
+    /// How the browser is sent to the URL holding the state
+    pub mode: StateUrlsMode,
+    /// Only handle `GET` requests that accept `text/html`
+    pub html_only: bool,
+}
+
+/// How the state URLs middleware makes cookie state visible in the URL
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StateUrlsMode {
+    /// Redirect to the URL with the state as query parameters
+    #[default]
+    Redirect,
+    /// Render the page from the state right away and replace the browser
+    /// URL with `history.replaceState` (or `HX-Replace-Url` for boosted
+    /// navigation)
+    ClientSync,
 }
 
 impl StateUrlsConfig {
@@ -20,6 +86,11 @@ impl StateUrlsConfig {
     pub fn new() -> Self {
         Self {
             denylist: Arc::new(HashSet::new()),
+            rules: Arc::new(Vec::new()),
+            allowlist_only: false,
+            pages: Arc::new(Vec::new()),
+            mode: StateUrlsMode::default(),
+            html_only: false,
         }
     }
 
@@ -31,6 +102,7 @@ impl StateUrlsConfig {
     {
         Self {
             denylist: Arc::new(items.into_iter().map(|s| s.into()).collect()),
+            ..Self::new()
         }
     }
 
@@ -47,6 +119,300 @@ impl StateUrlsConfig {
         self.denylist = Arc::new(denylist);
         self
     }
+
+    /// Choose between a redirect and client-side URL sync
+    pub fn mode(mut self, mode: StateUrlsMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Only handle `GET` requests that accept `text/html`, leaving
+    /// downloads, API calls and form posts alone
+    pub fn html_only(mut self, html_only: bool) -> Self {
+        self.html_only = html_only;
+        self
+    }
+
+    /// Exclude cookies matching a rule
+    pub fn rule(mut self, rule: CookieRule) -> Self {
+        let mut rules = (*self.rules).clone();
+        rules.push(rule);
+        self.rules = Arc::new(rules);
+        self
+    }
+
+    /// Exclude cookies whose name matches a glob (`*` and `?` wildcards)
+    pub fn deny_glob(self, pattern: impl Into<String>) -> Self {
+        self.rule(CookieRule::Glob(pattern.into()))
+    }
+
+    /// Exclude cookies whose name starts with `prefix`
+    pub fn deny_prefix(self, prefix: impl Into<String>) -> Self {
+        self.rule(CookieRule::Prefix(prefix.into()))
+    }
+
+    /// Exclude cookies whose name ends with `suffix`
+    pub fn deny_suffix(self, suffix: impl Into<String>) -> Self {
+        self.rule(CookieRule::Suffix(suffix.into()))
+    }
+
+    /// Exclude cookies whose value is longer than `max` bytes
+    pub fn max_value_len(self, max: usize) -> Self {
+        self.rule(CookieRule::MaxValueLen(max))
+    }
+
+    /// Exclude cookies whose value looks like a JSON Web Token
+    pub fn deny_jwt_like(self) -> Self {
+        self.rule(CookieRule::JwtLike)
+    }
+
+    /// Only promote cookies whose keys belong to the page's view states
+    ///
+    /// Pages without any mounted state get no state from cookies at all.
+    pub fn allowlist(mut self) -> Self {
+        self.allowlist_only = true;
+        self
+    }
+
+    /// Declare a component as mounted on a page, allowing its state keys there
+    ///
+    /// The page may be a route pattern with `{param}` segments.
+    /// Enables allowlist mode.
+    pub fn mount<C: ComponentName>(self, page: impl Into<String>) -> Self {
+        let keys = crate::component::component_info(C::name())
+            .map(|component| (component.state_fields)())
+            .unwrap_or_default();
+        self.allow(page, keys)
+    }
+
+    /// Allow the keys of a view state type on a page
+    ///
+    /// For state rendered by the page itself rather than a component.
+    /// Enables allowlist mode.
+    pub fn allow_state<T>(self, page: impl Into<String>) -> Self
+    where
+        T: serde::de::DeserializeOwned + 'static,
+    {
+        self.allow(page, crate::state::state_keys::<T>())
+    }
+
+    /// Allow explicit keys on a page. Enables allowlist mode.
+    pub fn allow<I, S>(mut self, page: impl Into<String>, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let page = page.into();
+        let mut pages = (*self.pages).clone();
+        let keys = keys.into_iter().map(Into::into);
+        match pages.iter_mut().find(|(pattern, _)| *pattern == page) {
+            Some((_, allowed)) => allowed.extend(keys),
+            None => pages.push((page, keys.collect())),
+        }
+        self.pages = Arc::new(pages);
+        self.allowlist_only = true;
+        self
+    }
+
+    /// Keys allowed on a page path, `None` when not in allowlist mode
+    pub fn allowed_keys(&self, path: &str) -> Option<HashSet<&str>> {
+        if !self.allowlist_only {
+            return None;
+        }
+        Some(
+            self.pages
+                .iter()
+                .filter(|(pattern, _)| route_matches(pattern, path))
+                .flat_map(|(_, keys)| keys.iter().map(String::as_str))
+                .collect(),
+        )
+    }
+
+    /// Explain whether a cookie would be promoted to the URL of `path`, and why
+    pub fn explain(&self, name: &str, value: &str, path: &str) -> CookieDecision {
+        self.decide(
+            name,
+            value,
+            &page_scope(path),
+            self.allowed_keys(path).as_ref(),
+        )
+    }
+
+    fn decide(
+        &self,
+        cookie_name: &str,
+        value: &str,
+        scope: &str,
+        allowed: Option<&HashSet<&str>>,
+    ) -> CookieDecision {
+        // Page-scoped cookies only apply to their own page
+        let name = match cookie_name.split_once(SCOPE_SEPARATOR) {
+            Some((cookie_scope, field)) if cookie_scope == scope => field,
+            Some((cookie_scope, _)) => {
+                return CookieDecision::Excluded(ExclusionReason::OtherPage(
+                    cookie_scope.to_string(),
+                ));
+            }
+            None => cookie_name,
+        };
+
+        if self.denylist.contains(name) {
+            return CookieDecision::Excluded(ExclusionReason::Denylisted);
+        }
+        if let Some(rule) = self.rules.iter().find(|rule| rule.matches(name, value)) {
+            return CookieDecision::Excluded(ExclusionReason::Rule(rule.clone()));
+        }
+        if allowed.is_some_and(|allowed| !allowed.contains(name)) {
+            return CookieDecision::Excluded(ExclusionReason::NotAllowed);
+        }
+        if value.is_empty() {
+            return CookieDecision::Excluded(ExclusionReason::Empty);
+        }
+        CookieDecision::Included(name.to_string())
+    }
+}
+
+/// A rule excluding cookies from state URLs
+#[derive(Clone, Debug)]
+pub enum CookieRule {
+    /// Name matches a glob with `*` (any run) and `?` (any character)
+    Glob(String),
+    /// Name starts with the prefix
+    Prefix(String),
+    /// Name ends with the suffix
+    Suffix(String),
+    /// Name matches the regular expression
+    Regex(regex::Regex),
+    /// Value is longer than this many bytes
+    MaxValueLen(usize),
+    /// Value has the shape of a JWT: three base64url segments, the first
+    /// starting with `eyJ` (an encoded `{"`)
+    JwtLike,
+}
+
+impl CookieRule {
+    /// Compile a name regex rule
+    pub fn regex(pattern: &str) -> Result<Self, regex::Error> {
+        regex::Regex::new(pattern).map(CookieRule::Regex)
+    }
+
+    /// Whether a cookie (name without page scope) matches this rule
+    pub fn matches(&self, name: &str, value: &str) -> bool {
+        match self {
+            CookieRule::Glob(pattern) => glob_matches(pattern.as_bytes(), name.as_bytes()),
+            CookieRule::Prefix(prefix) => name.starts_with(prefix.as_str()),
+            CookieRule::Suffix(suffix) => name.ends_with(suffix.as_str()),
+            CookieRule::Regex(regex) => regex.is_match(name),
+            CookieRule::MaxValueLen(max) => value.len() > *max,
+            CookieRule::JwtLike => is_jwt_like(value),
+        }
+    }
+}
+
+impl fmt::Display for CookieRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CookieRule::Glob(pattern) => write!(f, "name matches glob `{}`", pattern),
+            CookieRule::Prefix(prefix) => write!(f, "name starts with `{}`", prefix),
+            CookieRule::Suffix(suffix) => write!(f, "name ends with `{}`", suffix),
+            CookieRule::Regex(regex) => write!(f, "name matches regex `{}`", regex),
+            CookieRule::MaxValueLen(max) => write!(f, "value longer than {} bytes", max),
+            CookieRule::JwtLike => write!(f, "value looks like a JWT"),
+        }
+    }
+}
+
+/// Outcome of [`StateUrlsConfig::explain`]
+#[derive(Clone, Debug)]
+pub enum CookieDecision {
+    /// Promoted to the URL under this query key
+    Included(String),
+    /// Left out of the URL
+    Excluded(ExclusionReason),
+}
+
+/// Why a cookie was left out of the URL
+#[derive(Clone, Debug)]
+pub enum ExclusionReason {
+    /// Name is in the denylist
+    Denylisted,
+    /// Name or value matched a rule
+    Rule(CookieRule),
+    /// Allowlist mode is on and no state on the page uses the key
+    NotAllowed,
+    /// Scoped to another page
+    OtherPage(String),
+    /// Value is empty
+    Empty,
+}
+
+impl fmt::Display for CookieDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CookieDecision::Included(key) => write!(f, "included as `{}`", key),
+            CookieDecision::Excluded(reason) => write!(f, "excluded: {}", reason),
+        }
+    }
+}
+
+impl fmt::Display for ExclusionReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExclusionReason::Denylisted => write!(f, "name is denylisted"),
+            ExclusionReason::Rule(rule) => write!(f, "{}", rule),
+            ExclusionReason::NotAllowed => write!(f, "not a state key of this page"),
+            ExclusionReason::OtherPage(scope) => write!(f, "scoped to page `{}`", scope),
+            ExclusionReason::Empty => write!(f, "value is empty"),
+        }
+    }
+}
+
+fn glob_matches(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            glob_matches(&pattern[1..], name)
+                || (!name.is_empty() && glob_matches(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => glob_matches(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => glob_matches(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+fn is_jwt_like(value: &str) -> bool {
+    let segments: Vec<&str> = value.split('.').collect();
+    segments.len() == 3
+        && segments[0].starts_with("eyJ")
+        && segments[..2].iter().all(|s| !s.is_empty())
+        && segments.iter().all(|s| {
+            s.bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        })
+}
+
+/// State keys of the components routed at `path`
+fn route_state_fields(path: &str) -> Vec<String> {
+    inventory::iter::<crate::ComponentInfo>
+        .into_iter()
+        .filter(|component| route_matches(component.path, path))
+        .flat_map(|component| (component.state_fields)())
+        .collect()
+}
+
+/// Match a path against a route pattern where `{param}` matches one segment
+/// and a trailing `{*rest}` matches the remainder
+fn route_matches(pattern: &str, path: &str) -> bool {
+    let mut pattern_segments = pattern.trim_end_matches('/').split('/');
+    let mut path_segments = path.trim_end_matches('/').split('/');
+    loop {
+        match (pattern_segments.next(), path_segments.next()) {
+            (None, None) => return true,
+            (Some(p), _) if p.starts_with("{*") => return true,
+            (Some(p), Some(s)) if p == s || (p.starts_with('{') && p.ends_with('}')) => {}
+            _ => return false,
+        }
+    }
 }
 
 impl Default for StateUrlsConfig {
@@ -64,7 +430,12 @@ impl Default for StateUrlsConfig {
             "jwt",
             "bearer",
             "id",
+            SESSION_COOKIE,
+            STATE_COOKIE,
         ])
+        .deny_prefix("__Host-")
+        .deny_prefix("__Secure-")
+        .deny_jwt_like()
     }
 }
 
@@ -72,8 +443,14 @@ impl Default for StateUrlsConfig {
 ///
 /// This middleware:
 /// 1. Checks if the request has any query parameters
-/// 2. If not, loads values from cookies (excluding denylisted ones) and redirects
-/// 3. If yes, allows the request to proceed normally
+/// 2. If not, loads values from cookies (excluding denylisted and rule-matched
+///    ones, see [`StateUrlsConfig::explain`]) and redirects, or with
+///    [`StateUrlsMode::ClientSync`] renders the page and syncs the URL
+///    client-side
+/// 3. If yes, redirects to the canonical URL when it holds outdated params of
+///    a `#[view_state]` the route uses (a component routed there, or a state
+///    allowed on the page with `mount`/`allow_state`), otherwise allows the
+///    request to proceed normally
 ///
 /// This makes state from cookies immediately visible in the URL, enabling:
 /// - Bookmarkable URLs that preserve state
@@ -82,50 +459,160 @@ impl Default for StateUrlsConfig {
 ///
 /// # Security
 /// Sensitive cookies (tokens, session IDs, etc.) are excluded via the denylist
+/// and [`CookieRule`]s
 pub async fn state_urls_middleware_impl(
     config: Arc<StateUrlsConfig>,
     cookies: Cookies,
-    request: Request,
+    mut request: Request,
     next: Next,
 ) -> Response {
-    let uri = request.uri();
-    let path = uri.path();
+    if config.html_only && !accepts_html(&request) {
+        return next.run(request).await;
+    }
 
-    // Skip if this is an htmx request (already has state in URL or is a component update)
-    if request.headers().get("HX-Request").is_some() {
+    // Skip htmx requests (already have state in URL or are component
+    // updates), except boosted navigation when syncing client-side
+    let is_htmx = request.headers().contains_key("HX-Request");
+    let is_boosted = request.headers().contains_key("HX-Boosted");
+    if is_htmx && !(is_boosted && config.mode == StateUrlsMode::ClientSync) {
         return next.run(request).await;
     }
 
-    // Skip if query parameters already exist
-    if uri.query().is_some() {
+    let uri = request.uri().clone();
+    // Page path within the app, and the path the browser sees
+    let base = BasePrefix::from_extensions(request.extensions()).to_string();
+    let path = uri.path();
+    let browser_path = crate::base_path::join(&base, path);
+
+    // Skip if query parameters already exist, upgrading outdated
+    // `#[view_state]` params (old bookmarks) of the states the route uses
+    // to their canonical form; only safe methods, a redirect would turn a
+    // form `POST` into a `GET` and drop its body
+    if let Some(query) = uri.query() {
+        let is_safe = matches!(*request.method(), Method::GET | Method::HEAD);
+        let route_fields = route_state_fields(path);
+        let mut keys = config.allowed_keys(path).unwrap_or_default();
+        keys.extend(route_fields.iter().map(String::as_str));
+        if !is_htmx
+            && is_safe
+            && let Some(canonical) = crate::view_state::migrate_query(query, &keys)
+        {
+            let redirect_url = if canonical.is_empty() {
+                browser_path
+            } else {
+                let canonical = crate::compact::pack_query(&canonical);
+                format!("{}?{}", browser_path, canonical)
+            };
+            return Redirect::to(&redirect_url).into_response();
+        }
         return next.run(request).await;
     }
 
     // Collect cookies into query parameters, excluding denylisted ones
-    let mut query_params: HashMap<String, String> = HashMap::new();
+    let mut query_params: BTreeMap<String, String> = BTreeMap::new();
+    let scope = page_scope(path);
+    let allowed = config.allowed_keys(path);
 
     for cookie in cookies.list() {
-        let name = cookie.name();
-        let value = cookie.value();
-
-        // Skip denylisted cookies
-        if config.denylist.contains(name) {
-            continue;
+        let decision = config.decide(cookie.name(), cookie.value(), &scope, allowed.as_ref());
+        if let CookieDecision::Included(key) = decision {
+            query_params.insert(key, cookie.value().to_string());
         }
+    }
 
-        // Skip empty values
-        if !value.is_empty() {
-            query_params.insert(name.to_string(), value.to_string());
-        }
+    // No cookies found, proceed normally
+    if query_params.is_empty() {
+        return next.run(request).await;
     }
 
-    // If we have cookies, redirect to the same path with query params
-    if !query_params.is_empty() {
-        let query_string = serde_urlencoded::to_string(&query_params).unwrap_or_default();
-        let redirect_url = format!("{}?{}", path, query_string);
-        return Redirect::to(&redirect_url).into_response();
+    let mut query_string: QueryMap = query_params.into_iter().collect();
+    crate::compact::pack(&mut query_string);
+    let state_url = format!("{}?{}", browser_path, query_string);
+
+    if config.mode == StateUrlsMode::Redirect {
+        return Redirect::to(&state_url).into_response();
     }
 
-    // No cookies found, proceed normally
-    next.run(request).await
+    // Let the page see the state as if it had been requested with it
+    let request_url = format!("{}?{}", uri.path(), query_string);
+    let Ok(state_uri) = request_url.parse::<Uri>() else {
+        return next.run(request).await;
+    };
+    *request.uri_mut() = state_uri;
+    let response = next.run(request).await;
+
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+    if is_htmx {
+        with_replace_url(response, &state_url)
+    } else {
+        with_replace_state_script(response, &state_url).await
+    }
+}
+
+/// Whether the request is a `GET` for an HTML document
+fn accepts_html(request: &Request) -> bool {
+    request.method() == Method::GET
+        && request
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|accept| accept.contains("text/html"))
+}
+
+/// Tell htmx to show the state URL, unless the page chose its own
+fn with_replace_url(mut response: Response, url: &str) -> Response {
+    let headers = response.headers_mut();
+    if !headers.contains_key("HX-Push-Url")
+        && !headers.contains_key("HX-Replace-Url")
+        && let Ok(value) = HeaderValue::from_str(url)
+    {
+        headers.insert("HX-Replace-Url", value);
+    }
+    response
+}
+
+/// Inject a script replacing the browser URL into an HTML response
+///
+/// Only bodies of known length are rewritten; buffering a streamed body
+/// would hold it back until it completes.
+async fn with_replace_state_script(response: Response, url: &str) -> Response {
+    let is_html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/html"));
+    if !is_html || response.extensions().get::<crate::Streamed>().is_some() {
+        return response;
+    }
+    let Some(len) = HttpBody::size_hint(response.body()).exact() else {
+        return response;
+    };
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, len as usize).await else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to read response body",
+        )
+            .into_response();
+    };
+    let html = String::from_utf8_lossy(&bytes);
+
+    // JSON is a valid JS string literal; `</` must not close the script tag
+    let url_literal = serde_json::to_string(url)
+        .unwrap_or_default()
+        .replace("</", "<\\/");
+    let script = format!(
+        "<script>history.replaceState(history.state, \"\", {});</script>",
+        url_literal
+    );
+    let html = match html.find("</head>") {
+        Some(idx) => format!("{}{}{}", &html[..idx], script, &html[idx..]),
+        None => format!("{}{}", html, script),
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(html))
 }
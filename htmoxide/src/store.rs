@@ -0,0 +1,653 @@
+//! Storage backends for persisted view state
+//!
+//! A [`StateStore`] loads and saves the flattened fields of a view state.
+//! The app picks one store for client-side (`Persist::Cookie`) and one for
+//! server-side (`Persist::Session`) persistence:
+//!
+//! ```rust,ignore
+//! use htmoxide::{Persist, PersistConfig, store::{CookieBlobStore, SessionStore}};
+//! use htmoxide::tower_cookies::Key;
+//!
+//! let key = Key::generate();
+//! let app = htmoxide::app()
+//!     .route("/", get(index_page))
+//!     .htmx()
+//!     .persist_state(
+//!         PersistConfig::new(Persist::Cookie)
+//!             .cookie_store(CookieBlobStore::private(key))
+//!             .session_store(SessionStore::file("/var/lib/myapp/sessions")),
+//!     );
+//! ```
+//!
+//! Session backends expire sessions a day after they were last written and
+//! keep at most 10 000 of them by default; use [`MemoryBackend::new`] or
+//! [`FileBackend::with_limits`] with [`SessionStore::new`] to change that.
+
+use crate::persist::StoreContext;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use tower_cookies::{Cookie, Cookies, Key};
+
+/// Name of the cookie holding the server-side session id
+pub const SESSION_COOKIE: &str = "htmoxide_session";
+
+/// Name of the cookie holding the state blob of [`CookieBlobStore`]
+pub const STATE_COOKIE: &str = "htmoxide_state";
+
+/// Future returned by [`StateStore`] and [`SessionBackend`] methods
+pub type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Storage for persisted view state fields
+///
+/// `save` receives every scalar field of the current state; an empty value
+/// means the field should be cleared from the store. Keys are already
+/// namespaced when page scoping is enabled. Cookies written by a store should
+/// be created through `ctx.attributes` so the app's cookie settings apply.
+pub trait StateStore: Send + Sync {
+    /// Load all persisted fields
+    fn load<'a>(&'a self, ctx: &'a StoreContext<'_>) -> StoreFuture<'a, HashMap<String, String>>;
+
+    /// Persist the given fields
+    fn save<'a>(
+        &'a self,
+        ctx: &'a StoreContext<'_>,
+        values: &'a HashMap<String, String>,
+    ) -> StoreFuture<'a, ()>;
+}
+
+/// Stores each field in its own plain cookie
+///
+/// Values are visible and editable by users and subject to browser cookie
+/// size limits.
+#[derive(Clone, Debug, Default)]
+pub struct CookieStore;
+
+impl StateStore for CookieStore {
+    fn load<'a>(&'a self, ctx: &'a StoreContext<'_>) -> StoreFuture<'a, HashMap<String, String>> {
+        let values = ctx
+            .cookies
+            .list()
+            .into_iter()
+            .map(|c| (c.name().to_string(), c.value().to_string()))
+            .collect();
+        Box::pin(std::future::ready(values))
+    }
+
+    fn save<'a>(
+        &'a self,
+        ctx: &'a StoreContext<'_>,
+        values: &'a HashMap<String, String>,
+    ) -> StoreFuture<'a, ()> {
+        for (key, value) in values {
+            if value.is_empty() {
+                if ctx.cookies.get(key).is_some() {
+                    ctx.cookies.remove(ctx.attributes.removal(key.clone()));
+                }
+            } else {
+                ctx.cookies
+                    .add(ctx.attributes.cookie(key.clone(), value.clone()));
+            }
+        }
+        Box::pin(std::future::ready(()))
+    }
+}
+
+/// Stores all fields as a single JSON blob in one signed or encrypted cookie
+///
+/// Signed cookies can be read but not modified by users; private cookies can
+/// be neither read nor modified.
+#[derive(Clone)]
+pub struct CookieBlobStore {
+    key: Key,
+    encrypted: bool,
+}
+
+impl CookieBlobStore {
+    /// Sign the blob so users cannot tamper with it
+    pub fn signed(key: Key) -> Self {
+        Self {
+            key,
+            encrypted: false,
+        }
+    }
+
+    /// Encrypt the blob so users can neither read nor tamper with it
+    pub fn private(key: Key) -> Self {
+        Self {
+            key,
+            encrypted: true,
+        }
+    }
+
+    fn get(&self, cookies: &Cookies) -> Option<Cookie<'static>> {
+        if self.encrypted {
+            cookies.private(&self.key).get(STATE_COOKIE)
+        } else {
+            cookies.signed(&self.key).get(STATE_COOKIE)
+        }
+    }
+
+    fn blob(&self, cookies: &Cookies) -> HashMap<String, String> {
+        self.get(cookies)
+            .and_then(|cookie| serde_json::from_str(cookie.value()).ok())
+            .unwrap_or_default()
+    }
+
+    fn write(&self, ctx: &StoreContext, values: &HashMap<String, String>) {
+        let mut blob = self.blob(ctx.cookies);
+        merge_values(&mut blob, values);
+
+        if blob.is_empty() {
+            if ctx.cookies.get(STATE_COOKIE).is_some() {
+                ctx.cookies.remove(ctx.attributes.removal(STATE_COOKIE));
+            }
+            return;
+        }
+
+        let Ok(json) = serde_json::to_string(&blob) else {
+            return;
+        };
+        let cookie = ctx.attributes.cookie(STATE_COOKIE, json);
+        if self.encrypted {
+            ctx.cookies.private(&self.key).add(cookie);
+        } else {
+            ctx.cookies.signed(&self.key).add(cookie);
+        }
+    }
+}
+
+impl StateStore for CookieBlobStore {
+    fn load<'a>(&'a self, ctx: &'a StoreContext<'_>) -> StoreFuture<'a, HashMap<String, String>> {
+        Box::pin(std::future::ready(self.blob(ctx.cookies)))
+    }
+
+    fn save<'a>(
+        &'a self,
+        ctx: &'a StoreContext<'_>,
+        values: &'a HashMap<String, String>,
+    ) -> StoreFuture<'a, ()> {
+        self.write(ctx, values);
+        Box::pin(std::future::ready(()))
+    }
+}
+
+/// How long a session is kept after it was last written, by default
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Number of sessions kept before the oldest are evicted, by default
+pub const DEFAULT_MAX_SESSIONS: usize = 10_000;
+
+/// Backend holding session data for [`SessionStore`]
+pub trait SessionBackend: Send + Sync {
+    /// Load the fields stored for a session, empty once it expired
+    fn load<'a>(&'a self, session_id: &'a str) -> StoreFuture<'a, HashMap<String, String>>;
+
+    /// Apply changed fields to a session; empty values remove the field and
+    /// a session left without fields is deleted
+    ///
+    /// The read-modify-write is atomic, so concurrent requests of a session
+    /// don't lose each other's fields.
+    fn update<'a>(
+        &'a self,
+        session_id: &'a str,
+        values: &'a HashMap<String, String>,
+    ) -> StoreFuture<'a, ()>;
+}
+
+/// Keeps sessions in process memory; data is lost on restart
+///
+/// Sessions expire `ttl` after they were last written. Once `max_sessions`
+/// are held, expired sessions and then the least recently written ones are
+/// evicted to make room.
+pub struct MemoryBackend {
+    sessions: Mutex<HashMap<String, MemorySession>>,
+    ttl: Duration,
+    max_sessions: usize,
+}
+
+struct MemorySession {
+    values: HashMap<String, String>,
+    written: Instant,
+}
+
+impl MemoryBackend {
+    pub fn new(ttl: Duration, max_sessions: usize) -> Self {
+        Self {
+            sessions: Mutex::default(),
+            ttl,
+            max_sessions: max_sessions.max(1),
+        }
+    }
+
+    fn is_live(&self, session: &MemorySession) -> bool {
+        session.written.elapsed() < self.ttl
+    }
+}
+
+impl Default for MemoryBackend {
+    fn default() -> Self {
+        Self::new(DEFAULT_SESSION_TTL, DEFAULT_MAX_SESSIONS)
+    }
+}
+
+impl SessionBackend for MemoryBackend {
+    fn load<'a>(&'a self, session_id: &'a str) -> StoreFuture<'a, HashMap<String, String>> {
+        let values = self
+            .sessions
+            .lock()
+            .unwrap()
+            .get(session_id)
+            .filter(|session| self.is_live(session))
+            .map(|session| session.values.clone())
+            .unwrap_or_default();
+        Box::pin(std::future::ready(values))
+    }
+
+    fn update<'a>(
+        &'a self,
+        session_id: &'a str,
+        values: &'a HashMap<String, String>,
+    ) -> StoreFuture<'a, ()> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let mut stored = sessions
+            .remove(session_id)
+            .filter(|session| self.is_live(session))
+            .map(|session| session.values)
+            .unwrap_or_default();
+        merge_values(&mut stored, values);
+
+        if !stored.is_empty() {
+            if sessions.len() >= self.max_sessions {
+                sessions.retain(|_, session| self.is_live(session));
+            }
+            while sessions.len() >= self.max_sessions {
+                let Some(oldest) = sessions
+                    .iter()
+                    .min_by_key(|(_, session)| session.written)
+                    .map(|(id, _)| id.clone())
+                else {
+                    break;
+                };
+                sessions.remove(&oldest);
+            }
+            sessions.insert(
+                session_id.to_string(),
+                MemorySession {
+                    values: stored,
+                    written: Instant::now(),
+                },
+            );
+        }
+        Box::pin(std::future::ready(()))
+    }
+}
+
+/// Keeps each session as a JSON file in a directory
+///
+/// Files are replaced atomically (written aside, then renamed) off the async
+/// runtime. Sessions expire `ttl` after they were last written; once more
+/// than `max_sessions` files exist, expired and then the oldest are removed.
+pub struct FileBackend {
+    dir: PathBuf,
+    ttl: Duration,
+    max_sessions: usize,
+    /// Serializes updates; holds the number of session files once counted
+    files: tokio::sync::Mutex<Option<usize>>,
+}
+
+impl FileBackend {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self::with_limits(dir, DEFAULT_SESSION_TTL, DEFAULT_MAX_SESSIONS)
+    }
+
+    pub fn with_limits(dir: impl Into<PathBuf>, ttl: Duration, max_sessions: usize) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+            max_sessions: max_sessions.max(1),
+            files: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    fn path(&self, session_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", session_id))
+    }
+}
+
+impl SessionBackend for FileBackend {
+    fn load<'a>(&'a self, session_id: &'a str) -> StoreFuture<'a, HashMap<String, String>> {
+        let path = self.path(session_id);
+        let ttl = self.ttl;
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || read_session(&path, ttl))
+                .await
+                .unwrap_or_default()
+        })
+    }
+
+    fn update<'a>(
+        &'a self,
+        session_id: &'a str,
+        values: &'a HashMap<String, String>,
+    ) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            let mut files = self.files.lock().await;
+            let dir = self.dir.clone();
+            let path = self.path(session_id);
+            let values = values.clone();
+            let (ttl, max_sessions, known) = (self.ttl, self.max_sessions, *files);
+
+            let written = tokio::task::spawn_blocking(move || {
+                let mut count = match known {
+                    Some(count) => count,
+                    None => session_files(&dir).len(),
+                };
+                let existed = path.exists();
+                let mut stored = read_session(&path, ttl);
+                merge_values(&mut stored, &values);
+                write_session(&dir, &path, &stored)?;
+
+                match (existed, stored.is_empty()) {
+                    (false, false) => count += 1,
+                    (true, true) => count = count.saturating_sub(1),
+                    _ => {}
+                }
+                if count > max_sessions {
+                    count = evict_sessions(&dir, ttl, max_sessions);
+                }
+                Ok::<_, std::io::Error>(count)
+            })
+            .await;
+
+            match written {
+                Ok(Ok(count)) => *files = Some(count),
+                Ok(Err(e)) => {
+                    tracing::error!(dir = %self.dir.display(), error = %e, "failed to write session");
+                }
+                Err(e) => tracing::error!(error = %e, "session write task failed"),
+            }
+        })
+    }
+}
+
+/// Fields of a session file, empty if it is missing, unreadable or expired
+fn read_session(path: &Path, ttl: Duration) -> HashMap<String, String> {
+    let expired = std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .map(|modified| modified.elapsed().unwrap_or_default() >= ttl)
+        .unwrap_or(true);
+    if expired {
+        return HashMap::new();
+    }
+    std::fs::read(path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+/// Replace a session file, or remove it once the session has no fields
+fn write_session(dir: &Path, path: &Path, values: &HashMap<String, String>) -> std::io::Result<()> {
+    if values.is_empty() {
+        return match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+
+    std::fs::create_dir_all(dir)?;
+    // A crash mid-write leaves the temporary file, never a truncated session
+    let temporary = path.with_extension(format!("{}.tmp", new_session_id()));
+    let json = serde_json::to_vec(values).unwrap_or_default();
+    std::fs::write(&temporary, json)?;
+    std::fs::rename(&temporary, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temporary);
+    })
+}
+
+/// Session files in `dir` with the time they were last written
+fn session_files(dir: &Path) -> Vec<(PathBuf, SystemTime)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            let modified = std::fs::metadata(&path).ok()?.modified().ok()?;
+            Some((path, modified))
+        })
+        .collect()
+}
+
+/// Remove expired session files, then the oldest ones down to
+/// `max_sessions`, returning how many are left
+fn evict_sessions(dir: &Path, ttl: Duration, max_sessions: usize) -> usize {
+    let mut files = session_files(dir);
+    files.sort_by_key(|(_, modified)| *modified);
+    let excess = files.len().saturating_sub(max_sessions);
+    let mut left = files.len();
+    for (index, (path, modified)) in files.iter().enumerate() {
+        let expired = modified.elapsed().unwrap_or_default() >= ttl;
+        if (index < excess || expired) && std::fs::remove_file(path).is_ok() {
+            left -= 1;
+        }
+    }
+    left
+}
+
+/// Stores fields on the server, keyed by a random id in the session cookie
+pub struct SessionStore {
+    backend: Box<dyn SessionBackend>,
+}
+
+impl SessionStore {
+    /// Use a custom session backend
+    pub fn new(backend: impl SessionBackend + 'static) -> Self {
+        Self {
+            backend: Box::new(backend),
+        }
+    }
+
+    /// Keep sessions in process memory, see [`MemoryBackend`] for limits
+    pub fn memory() -> Self {
+        Self::new(MemoryBackend::default())
+    }
+
+    /// Keep sessions as JSON files in `dir`, see [`FileBackend`] for limits
+    pub fn file(dir: impl Into<PathBuf>) -> Self {
+        Self::new(FileBackend::new(dir))
+    }
+
+    /// Current session id, ignoring anything that is not one of ours
+    fn session_id(cookies: &Cookies) -> Option<String> {
+        cookies
+            .get(SESSION_COOKIE)
+            .map(|cookie| cookie.value().to_string())
+            .filter(|id| is_valid_session_id(id))
+    }
+}
+
+impl Default for SessionStore {
+    fn default() -> Self {
+        Self::memory()
+    }
+}
+
+impl StateStore for SessionStore {
+    fn load<'a>(&'a self, ctx: &'a StoreContext<'_>) -> StoreFuture<'a, HashMap<String, String>> {
+        let session_id = Self::session_id(ctx.cookies);
+        Box::pin(async move {
+            match session_id {
+                Some(id) => self.backend.load(&id).await,
+                None => HashMap::new(),
+            }
+        })
+    }
+
+    fn save<'a>(
+        &'a self,
+        ctx: &'a StoreContext<'_>,
+        values: &'a HashMap<String, String>,
+    ) -> StoreFuture<'a, ()> {
+        let session_id = match Self::session_id(ctx.cookies) {
+            Some(id) => id,
+            None => {
+                let id = new_session_id();
+                // The session id is never needed by scripts
+                let mut cookie = ctx.attributes.cookie(SESSION_COOKIE, id.clone());
+                cookie.set_http_only(true);
+                ctx.cookies.add(cookie);
+                id
+            }
+        };
+
+        Box::pin(async move { self.backend.update(&session_id, values).await })
+    }
+}
+
+/// Apply new values to stored ones; empty values remove the field
+fn merge_values(stored: &mut HashMap<String, String>, values: &HashMap<String, String>) {
+    for (key, value) in values {
+        if value.is_empty() {
+            stored.remove(key);
+        } else {
+            stored.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+/// Session ids are 32 lowercase hex characters, which also keeps them safe
+/// to use as file names
+fn is_valid_session_id(id: &str) -> bool {
+    id.len() == 32 && id.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Generate a random, hex-encoded session id
+fn new_session_id() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).expect("failed to generate session id");
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("htmoxide-sessions-{}", new_session_id()))
+    }
+
+    #[tokio::test]
+    async fn memory_backend_merges_updates() {
+        let backend = MemoryBackend::default();
+        backend
+            .update("a", &fields(&[("sort", "name"), ("page", "2")]))
+            .await;
+        backend.update("a", &fields(&[("page", "")])).await;
+
+        assert_eq!(backend.load("a").await, fields(&[("sort", "name")]));
+    }
+
+    #[tokio::test]
+    async fn memory_backend_expires_sessions() {
+        let backend = MemoryBackend::new(Duration::ZERO, 10);
+        backend.update("a", &fields(&[("sort", "name")])).await;
+
+        assert!(backend.load("a").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn memory_backend_evicts_the_oldest_session_at_the_cap() {
+        let backend = MemoryBackend::new(DEFAULT_SESSION_TTL, 2);
+        backend.update("a", &fields(&[("sort", "name")])).await;
+        backend.update("b", &fields(&[("sort", "date")])).await;
+        backend.update("a", &fields(&[("page", "2")])).await;
+        backend.update("c", &fields(&[("sort", "size")])).await;
+
+        assert!(backend.load("b").await.is_empty());
+        assert_eq!(backend.sessions.lock().unwrap().len(), 2);
+        assert_eq!(
+            backend.load("a").await,
+            fields(&[("sort", "name"), ("page", "2")])
+        );
+    }
+
+    #[tokio::test]
+    async fn file_backend_replaces_and_removes_sessions() {
+        let dir = temp_dir();
+        let backend = FileBackend::new(&dir);
+        let id = new_session_id();
+        backend.update(&id, &fields(&[("sort", "name")])).await;
+        backend.update(&id, &fields(&[("page", "2")])).await;
+
+        assert_eq!(
+            backend.load(&id).await,
+            fields(&[("sort", "name"), ("page", "2")])
+        );
+        // Only the session itself, no temporary files left behind
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        backend
+            .update(&id, &fields(&[("sort", ""), ("page", "")]))
+            .await;
+        assert!(!backend.path(&id).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn file_backend_keeps_concurrent_updates() {
+        let dir = temp_dir();
+        let backend = FileBackend::new(&dir);
+        let id = new_session_id();
+        let (sort, page) = (fields(&[("sort", "name")]), fields(&[("page", "2")]));
+        tokio::join!(backend.update(&id, &sort), backend.update(&id, &page));
+
+        assert_eq!(
+            backend.load(&id).await,
+            fields(&[("sort", "name"), ("page", "2")])
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn file_backend_expires_sessions() {
+        let dir = temp_dir();
+        let backend = FileBackend::with_limits(&dir, Duration::ZERO, 10);
+        let id = new_session_id();
+        backend.update(&id, &fields(&[("sort", "name")])).await;
+
+        assert!(backend.load(&id).await.is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn file_backend_evicts_the_oldest_session_past_the_cap() {
+        let dir = temp_dir();
+        let backend = FileBackend::with_limits(&dir, DEFAULT_SESSION_TTL, 1);
+        let (old, new) = (new_session_id(), new_session_id());
+        backend.update(&old, &fields(&[("sort", "name")])).await;
+        std::fs::File::options()
+            .write(true)
+            .open(backend.path(&old))
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(60))
+            .unwrap();
+        backend.update(&new, &fields(&[("sort", "date")])).await;
+
+        assert!(!backend.path(&old).exists());
+        assert_eq!(backend.load(&new).await, fields(&[("sort", "date")]));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
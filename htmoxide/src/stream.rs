@@ -0,0 +1,263 @@
+//! Streaming page responses
+//!
+//! [`Page`](crate::Page) renders the whole document before responding, so
+//! the slowest component decides when the browser sees anything.
+//! [`StreamingPage`] sends the layout and every component that is already
+//! done right away, and streams slow components into their slots as they
+//! finish:
+//!
+//! ```rust,ignore
+//! #[page("/dashboard")]
+//! async fn dashboard(ctx: RequestContext) -> StreamingPage {
+//!     let mut page = StreamingPage::new(&ctx);
+//!     let stats = page.slot(ctx.boundary(Stats).timeout(Duration::from_secs(5)));
+//!     let feed = page.defer(html! { "Loading feed…" }, load_feed());
+//!     page.body(html! {
+//!         head { /* ... */ }
+//!         body {
+//!             header { /* ... */ }
+//!             (stats)
+//!             (feed)
+//!         }
+//!     })
+//! }
+//! ```
+//!
+//! Until a slot is filled it shows the component's placeholder (see
+//! [`lazy`](mod@crate::lazy)). Filled slots arrive at the end of the document
+//! as hidden elements, moved into place by a small inline script.
+//!
+//! Clients that can't run the script get every slot rendered in place,
+//! streamed in document order: htmx requests (htmx swaps the response once
+//! complete) and clients not accepting `text/html`, such as crawlers and
+//! `curl`. Browsers with JavaScript disabled can't be told apart from the
+//! request; apps supporting them opt in with [`in_place`](StreamingPage::in_place),
+//! e.g. based on a cookie.
+//!
+//! The response carries the [`Streamed`] extension. Component wrappers and
+//! the state URLs middleware pass such responses through instead of
+//! buffering them, so polling, root checks and the `ClientSync` script don't
+//! apply to streaming pages.
+
+use crate::context::{Boundary, RequestContext};
+use axum::{
+    Extension,
+    body::{Body, Bytes},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use maud::{Markup, PreEscaped, Render};
+use std::convert::Infallible;
+use std::future::Future;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Marks a slot's position in the rendered body
+const SLOT_MARKER: &str = "<!--htmoxide-slot:";
+
+/// Moves a filled slot's content into place and lets htmx process it
+const FILL_SCRIPT: &str = "<script>function htmoxideFill(n){\
+var f=document.getElementById('htmoxide-fill-'+n),s=document.getElementById('htmoxide-slot-'+n);\
+if(!f||!s)return;var p=s.parentNode;\
+while(f.firstChild)p.insertBefore(f.firstChild,s);\
+s.remove();f.remove();if(window.htmx)htmx.process(p);}</script>";
+
+/// Full page response streaming slow components into slots
+pub struct StreamingPage {
+    in_place: bool,
+    slots: Vec<PendingSlot>,
+    body: Markup,
+}
+
+struct PendingSlot {
+    placeholder: Markup,
+    task: JoinHandle<Markup>,
+}
+
+/// Response extension marking a body that is streamed as it is produced
+///
+/// Layers rewriting HTML responses should pass these through unchanged:
+/// reading the body waits for the whole stream.
+#[derive(Clone, Copy, Debug)]
+pub struct Streamed;
+
+/// Position of a streamed component in the page body, see
+/// [`StreamingPage::slot`]
+#[derive(Clone, Copy, Debug)]
+pub struct Slot(usize);
+
+impl Render for Slot {
+    fn render(&self) -> Markup {
+        PreEscaped(format!("{}{}-->", SLOT_MARKER, self.0))
+    }
+}
+
+impl StreamingPage {
+    /// Streaming response to the request in `ctx`
+    pub fn new(ctx: &RequestContext) -> Self {
+        Self {
+            in_place: renders_in_place(ctx.headers()),
+            slots: Vec::new(),
+            body: PreEscaped(String::new()),
+        }
+    }
+
+    /// Render slots in place, in document order, without scripts
+    pub fn in_place(mut self) -> Self {
+        self.in_place = true;
+        self
+    }
+
+    /// Start rendering a component, to be embedded with the returned slot
+    ///
+    /// The component starts rendering right away, concurrently with the
+    /// other slots.
+    pub fn slot(&mut self, boundary: Boundary) -> Slot {
+        let placeholder = boundary.loading();
+        self.defer(placeholder, boundary.render())
+    }
+
+    /// Start rendering any markup, showing `placeholder` until it is done
+    pub fn defer(
+        &mut self,
+        placeholder: impl Render,
+        markup: impl Future<Output = Markup> + Send + 'static,
+    ) -> Slot {
+        self.slots.push(PendingSlot {
+            placeholder: placeholder.render(),
+            task: tokio::spawn(markup),
+        });
+        Slot(self.slots.len() - 1)
+    }
+
+    /// The page content, wrapped in `<html>` as with [`Page`](crate::Page)
+    pub fn body(mut self, markup: Markup) -> Self {
+        self.body = markup;
+        self
+    }
+
+    /// Send the body and fill the slots as their components finish
+    async fn stream(self, tx: mpsc::Sender<Bytes>) {
+        let send = |chunk: String| {
+            let tx = tx.clone();
+            async move { tx.send(Bytes::from(chunk)).await.is_ok() }
+        };
+
+        let in_place = self.in_place;
+        let body = self.body.into_string();
+        let mut slots: Vec<Option<PendingSlot>> = self.slots.into_iter().map(Some).collect();
+        let mut pending = Vec::new();
+        let mut chunk = String::from("<!DOCTYPE html><html>");
+        for (text, index) in split_slots(&body) {
+            chunk.push_str(text);
+            let Some((n, slot)) = index.and_then(|n| Some((n, slots.get_mut(n)?.take()?))) else {
+                continue;
+            };
+
+            if in_place || slot.task.is_finished() {
+                // Flush what precedes a slow slot before waiting on it
+                if !slot.task.is_finished() && !send(std::mem::take(&mut chunk)).await {
+                    return;
+                }
+                chunk.push_str(&output(slot.task).await.into_string());
+            } else {
+                chunk.push_str(&format!(
+                    r#"<div id="htmoxide-slot-{}" class="htmoxide-slot" aria-busy="true">{}</div>"#,
+                    n,
+                    slot.placeholder.into_string()
+                ));
+                pending.push((n, slot.task));
+            }
+        }
+
+        if !pending.is_empty() {
+            chunk.push_str(FILL_SCRIPT);
+            if !send(std::mem::take(&mut chunk)).await {
+                return;
+            }
+
+            // Fill slots in the order their components finish
+            let (filled_tx, mut filled) = mpsc::channel(pending.len());
+            for (n, task) in pending {
+                let filled_tx = filled_tx.clone();
+                tokio::spawn(async move {
+                    let _ = filled_tx.send((n, output(task).await)).await;
+                });
+            }
+            drop(filled_tx);
+            while let Some((n, markup)) = filled.recv().await {
+                let fill = format!(
+                    r#"<div hidden id="htmoxide-fill-{}">{}</div><script>htmoxideFill({})</script>"#,
+                    n,
+                    markup.into_string(),
+                    n
+                );
+                if !send(fill).await {
+                    return;
+                }
+            }
+        }
+
+        chunk.push_str("</html>");
+        send(chunk).await;
+    }
+}
+
+impl IntoResponse for StreamingPage {
+    fn into_response(self) -> Response {
+        let (tx, rx) = mpsc::channel(16);
+        tokio::spawn(self.stream(tx));
+        let chunks = futures_util::stream::unfold(rx, |mut rx| async move {
+            rx.recv()
+                .await
+                .map(|chunk| (Ok::<_, Infallible>(chunk), rx))
+        });
+
+        (
+            StatusCode::OK,
+            Extension(Streamed),
+            [
+                (header::CONTENT_TYPE, "text/html; charset=utf-8"),
+                // Keep reverse proxies from buffering the stream
+                (header::HeaderName::from_static("x-accel-buffering"), "no"),
+            ],
+            Body::from_stream(chunks),
+        )
+            .into_response()
+    }
+}
+
+/// Result of a slot's task; boundaries catch component panics, so it only
+/// fails for `defer`red futures
+async fn output(task: JoinHandle<Markup>) -> Markup {
+    task.await.unwrap_or_else(|_| {
+        tracing::error!("streamed slot panicked");
+        PreEscaped(String::new())
+    })
+}
+
+/// Split a rendered body into the text before each slot marker and the
+/// slot's index
+fn split_slots(body: &str) -> Vec<(&str, Option<usize>)> {
+    let mut parts = Vec::new();
+    let mut rest = body;
+    while let Some(start) = rest.find(SLOT_MARKER) {
+        let after = &rest[start + SLOT_MARKER.len()..];
+        let Some(end) = after.find("-->") else {
+            break;
+        };
+        parts.push((&rest[..start], after[..end].parse().ok()));
+        rest = &after[end + 3..];
+    }
+    parts.push((rest, None));
+    parts
+}
+
+/// Whether the client can't run the script filling out-of-order slots
+fn renders_in_place(headers: &HeaderMap) -> bool {
+    let accepts_html = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+    headers.contains_key("HX-Request") || !accepts_html
+}
@@ -1,37 +1,262 @@
-use serde::de::DeserializeOwned;
-use std::collections::HashMap;
+use crate::base_path::{BasePrefix, PublicOrigin};
+use crate::hx::Swap;
+use crate::persist::{Persist, PersistConfig};
+use crate::poll::{POLL_PARAM, Poll};
+use crate::query::QueryMap;
+use crate::signed::Signing;
+use crate::state::FIELDS_PARAM;
+use axum::http::request::Parts;
+use maud::Markup;
+use serde::{Serialize, de::DeserializeOwned};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::time::Duration;
+use tower_cookies::Key;
 
 /// Trait for getting a component's name at compile time
+///
+/// Implemented by the route type `#[component]` generates: a unit struct, or
+/// a struct with one field per path parameter (`ToggleTodo { id }` for
+/// `/todos/{id}/toggle`).
 pub trait ComponentName {
+    /// Id of the element the component renders, from `#[component(root = "...")]`
+    const ROOT: Option<&'static str> = None;
+
+    /// How the component's response is swapped in, from `#[component(swap = "...")]`
+    const SWAP: Option<Swap> = None;
+
+    /// Whether `render!` embeds a placeholder instead, from `#[component(lazy)]`
+    const LAZY: bool = false;
+
+    /// Markup shown until the component loads, from `#[component(placeholder = ...)]`
+    const PLACEHOLDER: Option<fn() -> Markup> = None;
+
+    /// How the component refreshes itself, from `#[component(poll = "...")]`
+    const POLL: Option<Poll> = None;
+
+    /// Persistence override, from `#[component(persist = "...")]`
+    const PERSIST: Option<Persist> = None;
+
     fn name() -> &'static str;
+
+    /// Path parameter values of this route, by name
+    fn path_params(&self) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
 }
 
 /// Helper for building component URLs with merged query parameters
+///
+/// Built URLs are canonical: the same params always produce the same query
+/// string. Keys of the view state set with `with_state_schema()` come first
+/// in field declaration order, all others follow sorted by name, and params
+/// equal to the state's default value are left out.
 #[derive(Clone)]
 pub struct UrlBuilder {
     path: String,
-    all_params: HashMap<String, String>,
+    all_params: QueryMap,
     main_page_path: Option<String>,
+    /// Keys set through `with_params`; those left out of the URL are sent
+    /// in the `_fields` manifest
+    touched: BTreeSet<String>,
+    /// The app's persistence, see `PersistConfig`
+    app_persist: Persist,
+    /// Persistence of the target; the manifest is only needed with one
+    persist: Persist,
+    /// Key order of the view state
+    state_order: Vec<String>,
+    /// Default values of the view state's scalar fields
+    defaults: HashMap<String, String>,
+    /// Prefix the app is mounted under, see `HtmxRouterExt::base_path`
+    base: String,
+    /// Public origin of the current request, used by `absolute()`
+    request_origin: Option<String>,
+    /// Origin to prefix built URLs with
+    origin: Option<String>,
+    /// Encoded `#fragment`
+    fragment: Option<String>,
+    /// Signature to add, see `signed()`
+    signing: Option<Signing>,
+    /// Root element id and swap of the target component
+    root: Option<&'static str>,
+    swap: Option<Swap>,
+    /// Placeholder of the target component, see `htmoxide::lazy`
+    placeholder: Option<fn() -> Markup>,
+}
+
+/// A built URL would still contain a `{param}` placeholder of its route
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedPath(pub String);
+
+impl fmt::Display for UnresolvedPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unresolved path parameter in `{}`; use `for_component(Route {{ .. }})`",
+            self.0
+        )
+    }
 }
 
+impl std::error::Error for UnresolvedPath {}
+
+/// Route type of a page registered with `#[page]`
+pub trait PageName: ComponentName {}
+
 /// Get the route path for a component by name
+///
+/// This is the path within the app; `UrlBuilder` and `BasePrefix::url` add
+/// the base path the app is mounted under.
 pub fn component_route(component_name: &str) -> Option<&'static str> {
-    for component in inventory::iter::<crate::ComponentInfo> {
-        if component.name == component_name {
-            return Some(component.path);
-        }
-    }
-    None
+    crate::component::component_info(component_name).map(|component| component.path)
 }
 
 impl UrlBuilder {
     pub fn new(path: impl Into<String>, query_string: &str) -> Self {
-        let all_params = parse_query_string(query_string);
+        let mut all_params = QueryMap::parse(query_string);
+        // The manifest and signature describe a single request, don't carry
+        // them forward
+        all_params.remove(FIELDS_PARAM);
+        crate::signed::remove_signature(&mut all_params);
+        all_params.remove(POLL_PARAM);
+        crate::compact::expand(&mut all_params);
         Self {
             path: path.into(),
             all_params,
             main_page_path: None,
+            touched: BTreeSet::new(),
+            app_persist: Persist::None,
+            persist: Persist::None,
+            state_order: Vec::new(),
+            defaults: HashMap::new(),
+            base: String::new(),
+            request_origin: None,
+            origin: None,
+            fragment: None,
+            signing: None,
+            root: None,
+            swap: None,
+            placeholder: None,
+        }
+    }
+
+    /// Builder for the route serving the current request
+    ///
+    /// Like `from_parts`, but a route with path parameters resolves to the
+    /// request's own path, so `build()` points back at the same resource
+    /// (`/jobs/7/status` rather than `/jobs/{id}/status`). This is what the
+    /// `#[component]` macro passes as `UrlBuilder`.
+    pub fn for_request(route: &str, parts: &Parts) -> Self {
+        // Routers nested under the base path see the path without it
+        let path = if has_placeholder(route) {
+            parts.uri.path()
+        } else {
+            route
+        };
+        Self::from_parts(path, parts)
+    }
+
+    /// Create a builder for a route from the current request
+    ///
+    /// Carries the request's query params, uses the page that issued an htmx
+    /// request as the main page and applies the app's base path.
+    pub fn from_parts(path: impl Into<String>, parts: &Parts) -> Self {
+        let mut builder = Self::new(path, parts.uri.query().unwrap_or(""))
+            .with_base_path(BasePrefix::from_extensions(&parts.extensions));
+        builder.request_origin = PublicOrigin::from_parts(parts);
+        builder.app_persist = PersistConfig::from_extensions(&parts.extensions).mode;
+        builder.persist = builder.app_persist;
+        if parts.headers.contains_key("HX-Current-URL") {
+            builder.with_main_page(crate::persist::page_path(parts))
+        } else {
+            builder
+        }
+    }
+
+    /// Prefix every built URL with the path the app is mounted under
+    pub fn with_base_path(mut self, base: impl Into<String>) -> Self {
+        self.base = base.into();
+        self
+    }
+
+    /// Build absolute URLs, for emails, share links or `Location` headers
+    /// pointing elsewhere
+    ///
+    /// Uses the app's `public_origin()`; stays relative without one, rather
+    /// than trusting the request's `Host`. Use `with_origin` to set one
+    /// explicitly.
+    ///
+    /// ```ignore
+    /// url.for_page(ProjectPage { id: 7 }).absolute().build()
+    /// // Returns https://example.com/projects/7?filter=active
+    /// ```
+    pub fn absolute(mut self) -> Self {
+        if self.request_origin.is_none() {
+            tracing::warn!(
+                "absolute URL requested without a public origin, configure public_origin()"
+            );
         }
+        self.origin = self.request_origin.clone();
+        self
+    }
+
+    /// Build absolute URLs on the given origin (`https://example.com`)
+    pub fn with_origin(mut self, origin: impl Into<String>) -> Self {
+        self.origin = Some(origin.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Append a `#fragment` such as an element id; the value is encoded
+    ///
+    /// ```ignore
+    /// url.with_fragment(format!("todo-{}", todo.id)).build()
+    /// // Returns /?filter=active#todo-42
+    /// ```
+    pub fn with_fragment(mut self, fragment: impl AsRef<str>) -> Self {
+        let fragment = fragment.as_ref();
+        self.fragment = (!fragment.is_empty()).then(|| encode_fragment(fragment));
+        self
+    }
+
+    /// Sign every param of the URL, so the recipient can't change them
+    ///
+    /// The link is rejected by the [`Signed`](crate::Signed) extractor once
+    /// modified, or after `expires_in` if given. Fields of the state set with
+    /// `with_state_schema()` are signed even when left out as defaults.
+    ///
+    /// ```ignore
+    /// url.for_page(InvitePage)
+    ///     .with_state(&invite)
+    ///     .signed(&key, Duration::from_secs(86400))
+    ///     .absolute()
+    ///     .build()
+    /// // Returns https://example.com/invite?team=7&_signed=team&_exp=1767225600&_sig=...
+    /// ```
+    pub fn signed(mut self, key: &Key, expires_in: impl Into<Option<Duration>>) -> Self {
+        self.signing = Some(Signing::new(key, None, expires_in.into()));
+        self
+    }
+
+    /// Sign only the given params, leaving others free to change
+    pub fn signed_fields<F: ToString>(
+        mut self,
+        key: &Key,
+        fields: impl IntoIterator<Item = F>,
+        expires_in: impl Into<Option<Duration>>,
+    ) -> Self {
+        let fields = fields.into_iter().map(|f| f.to_string()).collect();
+        self.signing = Some(Signing::new(key, Some(fields), expires_in.into()));
+        self
+    }
+
+    /// Persistence of the target component, if it overrides the app's
+    ///
+    /// Component URLs only carry the `_fields` manifest when the target
+    /// persists its state. `for_component` reads the override from the route
+    /// type; the component macro sets it for the component's own builder.
+    pub fn with_persist(mut self, persist: Option<Persist>) -> Self {
+        self.persist = persist.unwrap_or(self.app_persist);
+        self
     }
 
     /// Create a new UrlBuilder with a specific main page path for push URL
@@ -40,37 +265,182 @@ impl UrlBuilder {
         self
     }
 
+    /// Carry the schema version of a `#[view_state]` type in built URLs
+    ///
+    /// Does nothing for unversioned state types. Included in
+    /// `with_state_schema()`.
+    pub fn with_state_version<T: 'static>(mut self) -> Self {
+        if let Some(info) = crate::view_state::view_state_info::<T>()
+            && info.version > 1
+        {
+            self.all_params
+                .insert(info.version_param.to_string(), info.version.to_string());
+        }
+        self
+    }
+
+    /// Order keys and omit defaults according to a view state type
+    ///
+    /// Also carries the `#[view_state]` version. The component macro calls
+    /// this with the component's view state type.
+    pub fn with_state_schema<T>(mut self) -> Self
+    where
+        T: DeserializeOwned + Serialize + Default + 'static,
+    {
+        self.state_order = crate::state::state_keys::<T>();
+        self.defaults = crate::state::scalar_fields(&T::default());
+        self.with_state_version::<T>()
+    }
+
     /// Merge new parameters with existing ones
+    ///
+    /// Empty values and values equal to the state's default are dropped from
+    /// the URL. When the target persists its state, component URLs list them
+    /// in the `_fields` manifest, so the server resets their persisted value.
     pub fn with_params<K, V>(mut self, params: impl IntoIterator<Item = (K, V)>) -> Self
     where
         K: Into<String>,
         V: ToString,
     {
         for (key, value) in params {
-            self.all_params.insert(key.into(), value.to_string());
+            let key = key.into();
+            self.touched.insert(key.clone());
+            self.all_params.insert(key, value.to_string());
         }
         self
     }
 
-    /// Create a new UrlBuilder for a different component using the function name
+    /// Set every parameter of a view state from a typed value
+    ///
+    /// Encoded the same way requests are hydrated, so field names are checked
+    /// against the state type at compile time:
+    ///
+    /// ```ignore
+    /// url.with_state(&TodoViewState { filter: "active".into() }).build_main_url()
+    /// ```
+    ///
+    /// Fields equal to the state's default are left out of the URL.
+    pub fn with_state<T>(mut self, state: &T) -> Self
+    where
+        T: DeserializeOwned + Serialize + Default + 'static,
+    {
+        for (key, value) in crate::state::scalar_fields(&T::default()) {
+            self.defaults.entry(key).or_insert(value);
+        }
+        let encoded = crate::state::encode_state(state);
+        for key in encoded.keys() {
+            self.touched.insert(key.to_string());
+            self.all_params.set_all(key, encoded.get_all(key));
+        }
+        // Old field names are superseded by the typed value
+        if let Some(info) = crate::view_state::view_state_info::<T>() {
+            for (old, _) in info.aliases {
+                self.all_params.remove(old);
+            }
+        }
+        self.with_state_version::<T>()
+    }
+
+    /// Change a view state starting from its value in the current params
+    ///
+    /// ```ignore
+    /// url.update_state::<TodoViewState>(|s| s.filter = "completed".into()).build()
+    /// ```
+    pub fn update_state<T>(self, update: impl FnOnce(&mut T)) -> Self
+    where
+        T: DeserializeOwned + Serialize + Default + 'static,
+    {
+        let mut state: T = crate::state::state_from_query(&self.all_params.to_string());
+        update(&mut state);
+        self.with_state(&state)
+    }
+
+    /// Set all values of a repeated parameter (`tag=a&tag=b`)
+    ///
+    /// An empty list drops the parameter and lists it in the manifest.
+    pub fn with_values<V: ToString>(
+        mut self,
+        key: impl Into<String>,
+        values: impl IntoIterator<Item = V>,
+    ) -> Self {
+        let key = key.into();
+        self.touched.insert(key.clone());
+        let values: Vec<String> = values.into_iter().map(|v| v.to_string()).collect();
+        if values.is_empty() {
+            self.all_params.set_all(key, [String::new()]);
+        } else {
+            self.all_params.set_all(key, values);
+        }
+        self
+    }
+
+    /// Create a new UrlBuilder for a different component using its route type
+    ///
+    /// Path parameters are fields of the route type, so they are checked at
+    /// compile time and percent-encoded into the path.
     ///
     /// # Example
     /// ```ignore
-    /// url.for_component(create_todo).build()
+    /// url.for_component(CreateTodo).build()
     /// // If the current URL is /todos/1?filter=active
     /// // This returns /todos/create?filter=active
+    ///
+    /// url.for_component(ToggleTodo { id: 42 }).build()
+    /// // Returns /todos/42/toggle?filter=active
     /// ```
-    pub fn for_component<F>(mut self, _component: F) -> Self
+    pub fn for_component<F>(mut self, component: F) -> Self
     where
         F: ComponentName,
     {
-        let component_name = F::name();
-        if let Some(route) = component_route(component_name) {
+        self = self.with_persist(F::PERSIST).with_target_schema(F::name());
+        self.root = F::ROOT;
+        self.swap = F::SWAP;
+        self.placeholder = F::PLACEHOLDER;
+        if let Some(route) = component_route(F::name()) {
             self.path = route.to_string();
+            for (name, value) in component.path_params() {
+                self = self.with_path_param(name, value);
+            }
         }
         self
     }
 
+    /// Take key order and defaults from the target's view state; the
+    /// current component's don't apply to another one's URL
+    fn with_target_schema(mut self, component_name: &str) -> Self {
+        match crate::component::component_info(component_name) {
+            Some(component) => {
+                self.state_order = (component.state_fields)();
+                self.defaults = (component.state_defaults)();
+            }
+            None => {
+                self.state_order.clear();
+                self.defaults.clear();
+            }
+        }
+        self
+    }
+
+    /// Point the builder at a page registered with `#[page]`
+    ///
+    /// Both `build()` and `build_main_url()` then use the page path.
+    ///
+    /// # Example
+    /// ```ignore
+    /// url.for_page(ProjectPage { id: 7 }).build()
+    /// // Returns /projects/7?filter=active
+    /// ```
+    pub fn for_page<P>(mut self, page: P) -> Self
+    where
+        P: PageName,
+    {
+        self = self.for_component(page);
+        self.main_page_path = Some(self.path.clone());
+        // Pages aren't hydrated, nothing to reset
+        self.persist = Persist::None;
+        self
+    }
+
     /// Create a new UrlBuilder for a different component, preserving state params
     /// (String-based version for dynamic use cases)
     ///
@@ -79,6 +449,8 @@ impl UrlBuilder {
     /// url.with_component("create_todo").build()
     /// ```
     pub fn with_component(mut self, component_name: &str) -> Self {
+        self.persist = self.app_persist;
+        self = self.with_target_schema(component_name);
         if let Some(route) = component_route(component_name) {
             self.path = route.to_string();
         }
@@ -87,124 +459,251 @@ impl UrlBuilder {
 
     /// Replace path parameters in the route template
     ///
+    /// The value is percent-encoded; for a `{*rest}` wildcard each segment is.
+    /// Prefer `for_component(Route { .. })`, which checks names at compile time.
+    ///
     /// # Example
     /// ```ignore
     /// url.with_component("toggle_todo").with_path_param("id", 42).build()
     /// // Returns /todos/42/toggle?filter=active
     /// ```
     pub fn with_path_param(mut self, param_name: &str, value: impl ToString) -> Self {
+        let value = value.to_string();
         let placeholder = format!("{{{}}}", param_name);
-        self.path = self.path.replace(&placeholder, &value.to_string());
+        self.path = self
+            .path
+            .replace(&placeholder, &encode_path_segment(&value));
+        let wildcard = format!("{{*{}}}", param_name);
+        let segments: Vec<String> = value.split('/').map(encode_path_segment).collect();
+        self.path = self.path.replace(&wildcard, &segments.join("/"));
         self
     }
 
     /// Build the final URL with all parameters
+    ///
+    /// A path parameter left unresolved, e.g. by `with_component("toggle_todo")`
+    /// without `with_path_param`, is logged and stays percent-encoded in the
+    /// path (`/todos/%7Bid%7D/toggle`), so the URL can't match another route.
+    /// Use `try_build` to handle it instead.
     pub fn build(self) -> String {
-        // Filter out empty values AND empty keys
-        let filtered_params: HashMap<_, _> = self
-            .all_params
-            .into_iter()
-            .filter(|(k, v)| !k.is_empty() && !v.is_empty())
-            .collect();
+        self.url_for(&self.path, self.persist != Persist::None)
+    }
 
-        if filtered_params.is_empty() {
-            return self.path;
+    /// Build the final URL, failing if a path parameter is unresolved
+    pub fn try_build(self) -> Result<String, UnresolvedPath> {
+        if has_placeholder(&self.path) {
+            return Err(UnresolvedPath(self.path));
         }
+        Ok(self.url_for(&self.path, self.persist != Persist::None))
+    }
+
+    /// Build URL for the main page (for hx-push-url)
+    pub fn build_main_url(self) -> String {
+        let main_page = self.main_page_path.as_deref().unwrap_or("/");
+        self.url_for(main_page, false)
+    }
 
-        let query_string = serde_urlencoded::to_string(&filtered_params).unwrap_or_default();
+    /// Build URL for a specific page path (for hx-push-url)
+    pub fn build_page_url(self, page_path: impl Into<String>) -> String {
+        self.url_for(&page_path.into(), false)
+    }
 
-        if query_string.is_empty() {
-            self.path
+    /// URL for a path with the canonical query string
+    ///
+    /// State keys come in declaration order, then the remaining keys sorted,
+    /// then (with `manifest`) the `_fields` manifest of touched keys that
+    /// were left out and finally the signature. Compact states are packed
+    /// before signing.
+    fn url_for(&self, path: &str, manifest: bool) -> String {
+        let escaped;
+        let path = if has_placeholder(path) {
+            tracing::error!("{}", UnresolvedPath(path.to_string()));
+            escaped = path.replace('{', "%7B").replace('}', "%7D");
+            &escaped
         } else {
-            format!("{}?{}", self.path, query_string)
+            path
+        };
+
+        let mut params = self.all_params.clone();
+        params.sort_by_key(|key| {
+            let position = self.state_order.iter().position(|k| k == key);
+            (position.unwrap_or(usize::MAX), key.to_string())
+        });
+
+        let mut omitted = BTreeSet::new();
+        params.retain(|key, value| {
+            let is_default = self.defaults.get(key).is_some_and(|d| d == value);
+            let keep = !value.is_empty() && !is_default;
+            if !keep && self.touched.contains(key) {
+                omitted.insert(key.to_string());
+            }
+            keep
+        });
+        // A repeated key keeping some values was not left out
+        omitted.retain(|key| !params.contains_key(key));
+        if manifest && !omitted.is_empty() {
+            let fields: Vec<&str> = omitted.iter().map(String::as_str).collect();
+            params.append(FIELDS_PARAM, fields.join(","));
+        }
+        crate::compact::pack(&mut params);
+        if let Some(signing) = &self.signing {
+            signing.sign(path, &mut params, &self.state_order);
+        }
+
+        // Every part is ASCII and percent-encoded, so the URL is safe in HTML
+        // attributes as well as in headers
+        let mut url = self.origin.clone().unwrap_or_default();
+        url.push_str(&crate::base_path::join(&self.base, path));
+        if !params.is_empty() {
+            url.push('?');
+            url.push_str(&params.to_string());
+        }
+        if let Some(fragment) = &self.fragment {
+            url.push('#');
+            url.push_str(fragment);
         }
+        url
     }
 
-    /// Build URL for the main page (for hx-push-url)
-    pub fn build_main_url(self) -> String {
-        let main_page = self.main_page_path.unwrap_or_else(|| "/".to_string());
+    /// Get parameters that are NOT part of the specified state type
+    ///
+    /// Useful for carrying other components' params as hidden fields:
+    ///
+    /// ```ignore
+    /// (preserve_params(&url.other_params::<TodoViewState>(), &[]))
+    /// ```
+    pub fn other_params<T: DeserializeOwned + 'static>(&self) -> QueryMap {
+        let mut params = self.all_params.clone();
+        params.retain(|key, _| key != FIELDS_PARAM && !crate::state::is_state_key::<T>(key));
+        params
+    }
 
-        // Filter out empty values AND empty keys
-        let filtered_params: HashMap<_, _> = self
-            .all_params
-            .into_iter()
-            .filter(|(k, v)| !k.is_empty() && !v.is_empty())
-            .collect();
+    /// Get the parameters belonging to the specified state type
+    pub fn state_params<T: DeserializeOwned + 'static>(&self) -> QueryMap {
+        let mut params = self.all_params.clone();
+        params.retain(|key, _| crate::state::is_state_key::<T>(key));
+        params
+    }
 
-        if filtered_params.is_empty() {
-            return main_page;
-        }
+    /// Path within the app, without query or base path
+    pub(crate) fn route_path(&self) -> &str {
+        &self.path
+    }
 
-        let query_string = serde_urlencoded::to_string(&filtered_params).unwrap_or_default();
+    /// Get all parameters
+    pub fn all_params(&self) -> &QueryMap {
+        &self.all_params
+    }
 
-        if query_string.is_empty() {
-            main_page
-        } else {
-            format!("{}?{}", main_page, query_string)
-        }
+    /// `hx-target` selector of the component set with `for_component`, if it
+    /// declares a root element: `#todo-container`
+    pub fn default_target(&self) -> Option<String> {
+        self.root.map(|id| format!("#{}", id))
     }
 
-    /// Build URL for a specific page path (for hx-push-url)
-    pub fn build_page_url(self, page_path: impl Into<String>) -> String {
-        let page_path = page_path.into();
+    /// `hx-swap` of the component set with `for_component`, if it declares one
+    pub fn default_swap(&self) -> Option<Swap> {
+        self.swap
+    }
 
-        // Filter out empty values AND empty keys
-        let filtered_params: HashMap<_, _> = self
-            .all_params
-            .into_iter()
-            .filter(|(k, v)| !k.is_empty() && !v.is_empty())
-            .collect();
+    /// Root element id of the component set with `for_component`
+    pub(crate) fn root(&self) -> Option<&'static str> {
+        self.root
+    }
 
-        if filtered_params.is_empty() {
-            return page_path;
-        }
+    /// Placeholder of the component set with `for_component`
+    pub(crate) fn placeholder(&self) -> Option<fn() -> Markup> {
+        self.placeholder
+    }
+}
 
-        let query_string = serde_urlencoded::to_string(&filtered_params).unwrap_or_default();
+/// Percent-encode a value for use as one path segment
+fn encode_path_segment(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
 
-        if query_string.is_empty() {
-            page_path
+/// Percent-encode a fragment, keeping the characters RFC 3986 allows in one
+/// except `'`, which would end single-quoted attributes
+fn encode_fragment(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        let allowed = byte.is_ascii_alphanumeric()
+            || matches!(
+                byte,
+                b'-' | b'.'
+                    | b'_'
+                    | b'~'
+                    | b'!'
+                    | b'$'
+                    | b'('
+                    | b')'
+                    | b'*'
+                    | b'+'
+                    | b','
+                    | b';'
+                    | b'='
+                    | b':'
+                    | b'@'
+                    | b'/'
+                    | b'?'
+            );
+        if allowed {
+            encoded.push(byte as char);
         } else {
-            format!("{}?{}", page_path, query_string)
+            encoded.push_str(&format!("%{:02X}", byte));
         }
     }
+    encoded
+}
 
-    /// Get parameters that are NOT part of the specified state type
-    /// This is useful for including other components' params as hidden fields
-    pub fn other_params<T: DeserializeOwned>(&self) -> HashMap<String, String> {
-        // Get keys that would be deserialized by type T
-        let query_string = serde_urlencoded::to_string(&self.all_params).unwrap_or_default();
-        let _component_state: Result<T, _> = serde_urlencoded::from_str(&query_string);
+/// Whether a path still holds a `{param}` template placeholder
+fn has_placeholder(path: &str) -> bool {
+    path.split('/')
+        .any(|segment| segment.starts_with('{') && segment.ends_with('}'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
 
-        // For now, we'll need to manually exclude known fields
-        // A better approach would use serde introspection, but that's complex
-        // For the simple case, we can provide a simpler method
-        self.all_params.clone()
+    fn parts(uri: &str) -> Parts {
+        Request::get(uri).body(()).unwrap().into_parts().0
     }
 
-    /// Get all parameters as a HashMap
-    pub fn all_params(&self) -> &HashMap<String, String> {
-        &self.all_params
+    #[test]
+    fn for_request_resolves_path_params_from_the_request() {
+        let url = UrlBuilder::for_request("/jobs/{id}/status", &parts("/jobs/7/status?tab=log"));
+        assert_eq!(url.build(), "/jobs/7/status?tab=log");
     }
-}
 
-fn parse_query_string(query: &str) -> HashMap<String, String> {
-    if query.is_empty() {
-        return HashMap::new();
+    #[test]
+    fn for_request_keeps_static_routes() {
+        let url = UrlBuilder::for_request("/stats", &parts("/stats?range=week"));
+        assert_eq!(url.build(), "/stats?range=week");
     }
 
-    query
-        .split('&')
-        .filter_map(|pair| {
-            if pair.is_empty() {
-                return None;
-            }
-            let mut parts = pair.splitn(2, '=');
-            let key = parts.next()?.to_string();
-            if key.is_empty() {
-                return None;
-            }
-            let value = parts.next().unwrap_or("").to_string();
-            Some((key, value))
-        })
-        .collect()
+    #[test]
+    fn unresolved_path_params_are_reported() {
+        let url = UrlBuilder::new("/todos/{id}/toggle", "filter=active");
+        assert_eq!(
+            url.clone().try_build(),
+            Err(UnresolvedPath("/todos/{id}/toggle".to_string()))
+        );
+        assert_eq!(url.build(), "/todos/%7Bid%7D/toggle?filter=active");
+    }
+
+    #[test]
+    fn path_params_are_encoded() {
+        let url = UrlBuilder::new("/files/{name}", "").with_path_param("name", "a b/c");
+        assert_eq!(url.try_build().unwrap(), "/files/a%20b%2Fc");
+    }
 }
@@ -0,0 +1,327 @@
+//! Schema versioning for view state
+//!
+//! Bookmarked and shared URLs outlive the structs they were built from.
+//! Annotate a view state with `#[view_state]` to rename fields and migrate
+//! old query strings and persisted values to the current schema:
+//!
+//! ```rust,ignore
+//! use htmoxide::prelude::*;
+//!
+//! #[view_state(version = 2, migrate = migrate_todo_state)]
+//! #[derive(Deserialize, Serialize, Default)]
+//! pub struct TodoViewState {
+//!     #[view_state(alias = "filter")]
+//!     #[serde(default)]
+//!     pub status: String,
+//! }
+//!
+//! // Called with the version the params were written with
+//! fn migrate_todo_state(from: u32, params: &mut HashMap<String, String>) {
+//!     if from < 2 && params.get("status").map(String::as_str) == Some("done") {
+//!         params.insert("status".into(), "completed".into());
+//!     }
+//! }
+//! ```
+//!
+//! Versioned states carry their version in the URL (`_v=2` by default, see
+//! `version_param`). Params without a version are taken as the current
+//! version, unless they use an old field name from an alias, which marks
+//! them as version 1.
+//!
+//! When a request had to be upgraded, component handlers redirect plain
+//! `GET` requests to the canonical URL and send `HX-Replace-Url` for htmx
+//! requests, so old links are replaced in the address bar.
+
+use crate::base_path::BasePrefix;
+use crate::compact::CompactEncoding;
+use crate::query::QueryMap;
+use axum::{
+    http::{HeaderValue, Method, StatusCode, Uri, request::Parts},
+    response::{IntoResponse, Redirect, Response},
+};
+use std::any::TypeId;
+use std::collections::{HashMap, HashSet};
+
+/// Signature of a migration function: `(from_version, params)`
+pub type MigrateFn = fn(u32, &mut HashMap<String, String>);
+
+/// Schema information registered by `#[view_state]`
+#[derive(Clone)]
+pub struct ViewStateInfo {
+    pub type_id: fn() -> TypeId,
+    pub name: &'static str,
+    pub version: u32,
+    pub version_param: &'static str,
+    /// `(old_name, index)` field renames, `index` pointing into `fields`
+    pub aliases: &'static [(&'static str, usize)],
+    /// Field names as serde reads them, `rename`/`rename_all` applied
+    pub fields: fn() -> &'static [&'static str],
+    pub migrate: Option<MigrateFn>,
+    /// Packing into a single param, see [`crate::compact`]
+    pub compact: Option<CompactEncoding>,
+}
+
+impl ViewStateInfo {
+    pub const fn new<T: 'static>(
+        name: &'static str,
+        version: u32,
+        version_param: &'static str,
+        aliases: &'static [(&'static str, usize)],
+        fields: fn() -> &'static [&'static str],
+        migrate: Option<MigrateFn>,
+    ) -> Self {
+        Self {
+            type_id: TypeId::of::<T>,
+            name,
+            version,
+            version_param,
+            aliases,
+            fields,
+            migrate,
+            compact: None,
+        }
+    }
+
+    /// Pack the state into `param` once the query exceeds `threshold` bytes
+    pub const fn compact(mut self, param: &'static str, threshold: usize) -> Self {
+        self.compact = Some(CompactEncoding { param, threshold });
+        self
+    }
+
+    /// `(old_name, new_name)` field renames
+    pub fn renames(&self) -> impl Iterator<Item = (&'static str, &'static str)> {
+        let fields = (self.fields)();
+        self.aliases
+            .iter()
+            .filter_map(|(old, index)| Some((*old, *fields.get(*index)?)))
+    }
+
+    /// Whether the params contain anything belonging to this state
+    fn is_present(&self, params: &HashMap<String, String>) -> bool {
+        params.contains_key(self.version_param)
+            || (self.fields)().iter().any(|f| params.contains_key(*f))
+            || self
+                .aliases
+                .iter()
+                .any(|(old, _)| params.contains_key(*old))
+    }
+
+    /// Upgrade params to the current schema, returning whether anything changed
+    pub fn upgrade(&self, params: &mut HashMap<String, String>) -> bool {
+        if !self.is_present(params) {
+            return false;
+        }
+        let mut changed = false;
+
+        // Renamed fields: the new name wins if both are present
+        for (old, new) in self.renames() {
+            if let Some(value) = params.remove(old) {
+                params.entry(new.to_string()).or_insert(value);
+                changed = true;
+            }
+        }
+
+        // Without a version, only old field names tell the params are outdated
+        let from = match params.get(self.version_param) {
+            Some(version) => version.parse::<u32>().unwrap_or(1),
+            None if changed => 1,
+            None => self.version,
+        };
+        if from < self.version {
+            if let Some(migrate) = self.migrate {
+                migrate(from, params);
+            }
+            changed = true;
+        }
+
+        if self.version > 1 && from != self.version {
+            params.insert(self.version_param.to_string(), self.version.to_string());
+        }
+
+        changed
+    }
+}
+
+// Global view state registry using inventory
+inventory::collect!(ViewStateInfo);
+
+/// Get the registered schema of a view state type, if it has one
+pub fn view_state_info<T: 'static>() -> Option<&'static ViewStateInfo> {
+    inventory::iter::<ViewStateInfo>
+        .into_iter()
+        .find(|info| (info.type_id)() == TypeId::of::<T>())
+}
+
+/// Upgrade a query string for the registered view states with a field
+/// among `keys`, such as the state keys a route uses
+///
+/// Returns the canonical query string if anything had to change.
+pub fn migrate_query(query: &str, keys: &HashSet<&str>) -> Option<String> {
+    let original = QueryMap::parse(&crate::compact::expand_query(query));
+    let mut params = original.to_map();
+    let mut changed = false;
+    for info in inventory::iter::<ViewStateInfo> {
+        if (info.fields)().iter().any(|field| keys.contains(field)) {
+            changed |= info.upgrade(&mut params);
+        }
+    }
+    changed.then(|| encode_params(&original, &params))
+}
+
+/// Upgrade a query string for one view state type
+///
+/// Returns the canonical query string if anything had to change.
+pub fn migrate_query_for<T: 'static>(query: &str) -> Option<String> {
+    let info = view_state_info::<T>()?;
+    let original = QueryMap::parse(&crate::compact::expand_query(query));
+    let mut params = original.to_map();
+    info.upgrade(&mut params)
+        .then(|| encode_params(&original, &params))
+}
+
+/// Canonical URLs to send back after a request's view state was upgraded
+#[derive(Clone, Debug)]
+pub struct StateMigration {
+    /// Canonical URL of the request itself (for redirects)
+    pub request_url: String,
+    /// Canonical URL of the page that issued an htmx request
+    pub page_url: Option<String>,
+}
+
+/// Upgrade the request's query string in place for view state `T`
+///
+/// The rewritten URI lets every later extractor (and the `UrlBuilder`) see
+/// the canonical params. A [`StateMigration`] is left in the extensions.
+pub(crate) fn migrate_request<T: 'static>(parts: &mut Parts) {
+    let Some(canonical) = migrate_query_for::<T>(parts.uri.query().unwrap_or("")) else {
+        return;
+    };
+
+    if let Ok(uri) = with_query(parts.uri.path(), &canonical).parse::<Uri>() {
+        parts.uri = uri;
+    }
+    // The browser sees the path under the app's base path
+    let base = BasePrefix::from_extensions(&parts.extensions);
+    let browser_path = crate::base_path::join(base, parts.uri.path());
+    let request_url = with_query(&browser_path, &crate::compact::pack_query(&canonical));
+
+    // htmx requests carry the page URL, which holds the same outdated params
+    let page_url = parts
+        .headers
+        .get("HX-Current-URL")
+        .and_then(|v| v.to_str().ok())
+        .map(|url| {
+            let url = url.split('#').next().unwrap_or(url);
+            let (path, query) = url.split_once('?').unwrap_or((url, ""));
+            match migrate_query_for::<T>(query) {
+                Some(canonical) => with_query(path, &crate::compact::pack_query(&canonical)),
+                None => url.to_string(),
+            }
+        });
+
+    parts.extensions.insert(StateMigration {
+        request_url,
+        page_url,
+    });
+}
+
+impl StateMigration {
+    /// Redirect plain (non-htmx) `GET` requests to the canonical URL
+    ///
+    /// Components rendered inline by a page are never redirected.
+    pub fn redirect(&self, parts: &Parts) -> Option<Response> {
+        let is_htmx = parts.headers.contains_key("HX-Request");
+        let is_inline = parts
+            .extensions
+            .get::<crate::context::InlineRender>()
+            .is_some();
+        (parts.method == Method::GET && !is_htmx && !is_inline)
+            .then(|| Redirect::to(&self.request_url).into_response())
+    }
+
+    /// Tell htmx to replace the page URL with its canonical form
+    pub fn apply(&self, mut response: Response) -> Response {
+        if response.status() == StatusCode::OK
+            && !response.headers().contains_key("HX-Push-Url")
+            && let Some(page_url) = &self.page_url
+            && let Ok(value) = HeaderValue::from_str(page_url)
+        {
+            response.headers_mut().insert("HX-Replace-Url", value);
+        }
+        response
+    }
+}
+
+fn with_query(path: &str, query: &str) -> String {
+    if query.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}?{}", path, query)
+    }
+}
+
+/// Encode upgraded params, keeping every value of keys the upgrade left alone
+fn encode_params(original: &QueryMap, upgraded: &HashMap<String, String>) -> String {
+    let mut query = QueryMap::new();
+    for (key, value) in original {
+        if upgraded.get(key).map(String::as_str) == original.get(key) {
+            query.append(key, value);
+        }
+    }
+    for (key, value) in upgraded {
+        if !query.contains_key(key) {
+            query.append(key.as_str(), value.as_str());
+        }
+    }
+    // Sorted so the canonical URL is stable
+    query.sort_by_key(str::to_string);
+    query.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrate(from: u32, params: &mut HashMap<String, String>) {
+        if from < 2 && params.get("status").map(String::as_str) == Some("done") {
+            params.insert("status".into(), "completed".into());
+        }
+    }
+
+    const TODO: ViewStateInfo = ViewStateInfo::new::<()>(
+        "TodoState",
+        2,
+        "_v",
+        &[("filter", 0)],
+        || &["status"],
+        Some(migrate),
+    );
+
+    fn params(query: &str) -> HashMap<String, String> {
+        QueryMap::parse(query).to_map()
+    }
+
+    #[test]
+    fn unversioned_params_are_current() {
+        let mut current = params("status=done");
+        assert!(!TODO.upgrade(&mut current));
+        assert_eq!(current, params("status=done"));
+    }
+
+    #[test]
+    fn old_field_names_mark_version_one() {
+        let mut old = params("filter=done");
+        assert!(TODO.upgrade(&mut old));
+        assert_eq!(old, params("status=completed&_v=2"));
+    }
+
+    #[test]
+    fn explicit_versions_are_migrated() {
+        let mut old = params("status=done&_v=1");
+        assert!(TODO.upgrade(&mut old));
+        assert_eq!(old, params("status=completed&_v=2"));
+
+        let mut current = params("status=done&_v=2");
+        assert!(!TODO.upgrade(&mut current));
+    }
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use axum::{body::Body, response::Response, routing::get};
+use htmoxide::prelude::*;
+use htmoxide::{StateUrlsConfig, StateUrlsMode};
+use std::convert::Infallible;
+
+const PAGE: &str = "<!DOCTYPE html><html><head></head><body>page</body></html>";
+
+async fn buffered() -> Response {
+    Response::builder()
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::from(PAGE))
+        .unwrap()
+}
+
+async fn streamed() -> Response {
+    let chunks = futures_util::stream::iter([Ok::<_, Infallible>(PAGE)]);
+    Response::builder()
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::from_stream(chunks))
+        .unwrap()
+}
+
+fn app() -> axum::Router {
+    htmoxide::app()
+        .route("/buffered", get(buffered))
+        .route("/streamed", get(streamed))
+        .with_state_urls_custom(StateUrlsConfig::default().mode(StateUrlsMode::ClientSync))
+        .htmx()
+}
+
+#[tokio::test]
+async fn injects_the_url_script_into_buffered_pages() {
+    let response = common::get(&app(), "/buffered", &[("cookie", "count=3")]).await;
+
+    assert!(
+        response
+            .body
+            .contains(r#"history.replaceState(history.state, "", "/buffered?count=3")"#)
+    );
+}
+
+#[tokio::test]
+async fn passes_streamed_pages_through() {
+    let response = common::get(&app(), "/streamed", &[("cookie", "count=3")]).await;
+
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, PAGE);
+}
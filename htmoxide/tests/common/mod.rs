@@ -0,0 +1,53 @@
+#![allow(dead_code)]
+
+use axum::{
+    Router,
+    body::Body,
+    http::{HeaderMap, Request, StatusCode},
+};
+use tower::ServiceExt;
+
+/// Response of a test request, with the body as text
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+/// Send a `GET` request with extra headers through the app
+pub async fn get(app: &Router, uri: &str, headers: &[(&str, &str)]) -> TestResponse {
+    let mut request = Request::get(uri);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    send(app, request.body(Body::empty()).unwrap()).await
+}
+
+/// Send a `POST` request with a form body through the app
+pub async fn post_form(app: &Router, uri: &str, form: &str) -> TestResponse {
+    let request = Request::post(uri)
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(Body::from(form.to_string()))
+        .unwrap();
+    send(app, request).await
+}
+
+/// Send a request through the app
+pub async fn send(app: &Router, request: Request<Body>) -> TestResponse {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    TestResponse {
+        status,
+        headers,
+        body: String::from_utf8(bytes.to_vec()).unwrap(),
+    }
+}
+
+/// `GET` as htmx sends it
+pub async fn hx_get(app: &Router, uri: &str) -> TestResponse {
+    get(app, uri, &[("HX-Request", "true")]).await
+}
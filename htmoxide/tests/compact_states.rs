@@ -0,0 +1,66 @@
+mod common;
+
+use htmoxide::QueryMap;
+use htmoxide::prelude::*;
+
+#[view_state(compact_threshold = 10)]
+#[derive(Deserialize, Serialize, Default)]
+struct FilterState {
+    status: String,
+    tag: String,
+}
+
+#[view_state(compact_threshold = 10)]
+#[derive(Deserialize, Serialize, Default)]
+struct SortState {
+    column: String,
+    descending: bool,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+struct PlainState {
+    sort_state: String,
+}
+
+#[component]
+async fn filters(state: FilterState, _url: UrlBuilder) -> Html {
+    html! { (state.status) ":" (state.tag) }.into()
+}
+
+#[component]
+async fn sorting(state: SortState, _url: UrlBuilder) -> Html {
+    html! { (state.column) ":" (state.descending) }.into()
+}
+
+#[component]
+async fn plain(state: PlainState, _url: UrlBuilder) -> Html {
+    html! { (state.sort_state) }.into()
+}
+
+#[tokio::test]
+async fn packs_each_compact_state_into_its_own_param() {
+    let url = UrlBuilder::new(
+        "/",
+        "status=open&tag=urgent&column=due&descending=true&page=2",
+    )
+    .build();
+
+    let query = QueryMap::parse(url.split_once('?').unwrap().1);
+    let mut keys = query.keys();
+    keys.sort();
+    assert_eq!(keys, ["filter_state", "page", "sort_state"]);
+
+    let app = htmoxide::app().htmx();
+    let filters = common::hx_get(&app, &format!("/filters?{}", query)).await;
+    assert_eq!(filters.body, "open:urgent");
+    let sorting = common::hx_get(&app, &format!("/sorting?{}", query)).await;
+    assert_eq!(sorting.body, "due:true");
+}
+
+#[tokio::test]
+async fn keeps_plain_params_named_like_a_compact_param() {
+    let app = htmoxide::app().htmx();
+    let response = common::hx_get(&app, "/plain?sort_state=priority").await;
+
+    assert_eq!(response.body, "priority");
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use htmoxide::hx;
+use htmoxide::prelude::*;
+
+#[derive(Deserialize, Serialize, Default)]
+struct FilterState {
+    filter: String,
+}
+
+#[component(path = "/todos")]
+async fn todos(_state: FilterState, url: UrlBuilder) -> Html {
+    hx::get(url.with_params([("filter", "")]))
+        .push_main()
+        .a("All")
+        .into()
+}
+
+#[component(path = "/notes", persist = "cookie")]
+async fn notes(_state: FilterState, url: UrlBuilder) -> Html {
+    hx::get(url.with_params([("filter", "")]))
+        .push_main()
+        .a("All")
+        .into()
+}
+
+#[derive(Deserialize, Serialize)]
+struct PagerState {
+    page: u32,
+}
+
+impl Default for PagerState {
+    fn default() -> Self {
+        Self { page: 1 }
+    }
+}
+
+#[component(path = "/pager")]
+async fn pager(state: PagerState, _url: UrlBuilder) -> Html {
+    html! { (state.page) }.into()
+}
+
+#[component(path = "/links")]
+async fn links(_state: FilterState, url: UrlBuilder) -> Html {
+    html! { (url.for_component(Pager).with_params([("page", "1")]).build()) }.into()
+}
+
+#[tokio::test]
+async fn leaves_the_manifest_out_without_persistence() {
+    let app = htmoxide::app().htmx();
+    let response = common::hx_get(&app, "/todos?filter=active").await;
+
+    assert_eq!(
+        response.body,
+        r#"<a href="/" hx-get="/todos" hx-push-url="/">All</a>"#
+    );
+}
+
+#[tokio::test]
+async fn lists_omitted_fields_on_component_urls_when_persisting() {
+    let app = htmoxide::app().htmx().persist_state(Persist::Cookie);
+    let response = common::hx_get(&app, "/todos?filter=active").await;
+
+    assert_eq!(
+        response.body,
+        r#"<a href="/" hx-get="/todos?_fields=filter" hx-push-url="/">All</a>"#
+    );
+}
+
+#[tokio::test]
+async fn honours_the_component_persistence_override() {
+    let app = htmoxide::app().htmx();
+    let response = common::hx_get(&app, "/notes?filter=active").await;
+
+    assert_eq!(
+        response.body,
+        r#"<a href="/" hx-get="/notes?_fields=filter" hx-push-url="/">All</a>"#
+    );
+}
+
+#[tokio::test]
+async fn omits_the_defaults_of_the_target_component() {
+    let app = htmoxide::app().htmx();
+    let response = common::hx_get(&app, "/links").await;
+
+    assert_eq!(response.body, "/pager");
+}
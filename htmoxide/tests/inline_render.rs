@@ -0,0 +1,62 @@
+mod common;
+
+use axum::extract::Path;
+use htmoxide::prelude::*;
+
+#[derive(Deserialize, Serialize, Default)]
+struct JobState {
+    tab: String,
+}
+
+#[component(path = "/jobs/{id}/detail")]
+async fn job_detail(state: JobState, url: UrlBuilder, Path(id): Path<u32>) -> Html {
+    html! { div { "job " (id) " tab " (state.tab) " at " (url.build()) } }.into()
+}
+
+#[page("/jobs/{id}")]
+async fn job_page(ctx: RequestContext, Path(id): Path<u32>) -> Html {
+    let detail = ctx.try_render(JobDetail { id }).await;
+    html! {
+        main {
+            (render!(ctx, JobDetail { id: id + 1 }))
+            (format!("{:?}", detail.as_ref().map(|m| m.0.as_str())))
+        }
+    }
+    .into()
+}
+
+#[tokio::test]
+async fn renders_parametric_components_inline() {
+    let app = htmoxide::app().htmx();
+    let response = common::get(&app, "/jobs/7?tab=log", &[]).await;
+
+    assert_eq!(response.status, 200);
+    assert!(
+        response
+            .body
+            .contains("<div>job 8 tab log at /jobs/8/detail?tab=log</div>"),
+        "{}",
+        response.body
+    );
+    assert!(
+        response
+            .body
+            .contains("Ok(&quot;&lt;div&gt;job 7 tab log at /jobs/7/detail?tab=log"),
+        "{}",
+        response.body
+    );
+}
+
+#[tokio::test]
+async fn renders_parametric_components_inline_under_the_base_path() {
+    let app = htmoxide::app().htmx().base_path("/tools");
+    let response = common::get(&app, "/tools/jobs/7", &[]).await;
+
+    assert!(
+        response
+            .body
+            .contains("<div>job 8 tab  at /tools/jobs/8/detail</div>"),
+        "{}",
+        response.body
+    );
+}
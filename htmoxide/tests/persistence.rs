@@ -0,0 +1,94 @@
+mod common;
+
+use htmoxide::prelude::*;
+use htmoxide::store::{CookieBlobStore, SESSION_COOKIE, STATE_COOKIE, SessionStore};
+use htmoxide::tower_cookies::{Key, cookie::SameSite};
+use htmoxide::{PersistConfig, StateScope};
+
+#[derive(Deserialize, Serialize, Default)]
+struct SortState {
+    #[serde(default)]
+    sort: String,
+}
+
+#[component(path = "/list")]
+async fn list(state: SortState, _url: UrlBuilder) -> Html {
+    html! { (state.sort) }.into()
+}
+
+/// Every `Set-Cookie` header of the response
+fn set_cookies(response: &common::TestResponse) -> Vec<String> {
+    response
+        .headers
+        .get_all("set-cookie")
+        .iter()
+        .map(|value| value.to_str().unwrap().to_string())
+        .collect()
+}
+
+fn cookie<'a>(cookies: &'a [String], name: &str) -> &'a str {
+    let header = cookies
+        .iter()
+        .find(|c| c.starts_with(&format!("{}=", name)))
+        .unwrap_or_else(|| panic!("no {} cookie in {:?}", name, cookies));
+    header.split(';').next().unwrap()
+}
+
+async fn hx_get_with_cookie(app: &axum::Router, uri: &str, cookie: &str) -> common::TestResponse {
+    common::get(app, uri, &[("HX-Request", "true"), ("Cookie", cookie)]).await
+}
+
+#[tokio::test]
+async fn restores_fields_from_the_session() {
+    let app = htmoxide::app()
+        .htmx()
+        .persist_state(PersistConfig::new(Persist::Session).session_store(SessionStore::memory()));
+    let first = common::hx_get(&app, "/list?sort=name").await;
+    let cookies = set_cookies(&first);
+    let session = cookie(&cookies, SESSION_COOKIE);
+    assert!(cookies[0].contains("HttpOnly"));
+
+    let second = hx_get_with_cookie(&app, "/list", session).await;
+    assert_eq!(second.body, "name");
+}
+
+#[tokio::test]
+async fn ignores_a_tampered_state_blob() {
+    let app = htmoxide::app().htmx().persist_state(
+        PersistConfig::new(Persist::Cookie).cookie_store(CookieBlobStore::signed(Key::generate())),
+    );
+    let first = common::hx_get(&app, "/list?sort=name").await;
+    let cookies = set_cookies(&first);
+    let blob = cookie(&cookies, STATE_COOKIE);
+
+    let second = hx_get_with_cookie(&app, "/list", blob).await;
+    assert_eq!(second.body, "name");
+
+    let tampered = blob.replace("name", "size");
+    let third = hx_get_with_cookie(&app, "/list", &tampered).await;
+    assert_eq!(third.body, "");
+}
+
+#[tokio::test]
+async fn applies_cookie_attributes_and_page_scope() {
+    let app = htmoxide::app().htmx().persist_state(
+        PersistConfig::new(Persist::Cookie)
+            .scope(StateScope::Page)
+            .secure(true)
+            .same_site(SameSite::Strict),
+    );
+    let response = common::get(
+        &app,
+        "/list?sort=name",
+        &[
+            ("HX-Request", "true"),
+            ("HX-Current-URL", "http://localhost/projects"),
+        ],
+    )
+    .await;
+    let cookies = set_cookies(&response);
+
+    assert_eq!(cookie(&cookies, "projects~sort"), "projects~sort=name");
+    assert!(cookies[0].contains("Secure"));
+    assert!(cookies[0].contains("SameSite=Strict"));
+}
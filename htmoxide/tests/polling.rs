@@ -0,0 +1,76 @@
+mod common;
+
+use axum::extract::Path;
+use htmoxide::prelude::*;
+
+#[derive(Deserialize, Serialize, Default)]
+struct JobState {
+    tab: String,
+}
+
+#[component(path = "/jobs/{id}/status", root = "job", poll = "2s")]
+async fn job_status(_state: JobState, _url: UrlBuilder, Path(id): Path<u32>) -> Html {
+    html! { div #job { "job " (id) } }.into()
+}
+
+#[component(path = "/jobs/{id}/log", poll = "1s", backoff = "4s")]
+async fn job_log(_state: JobState, _url: UrlBuilder, Path(id): Path<u32>) -> Html {
+    html! { pre { "log " (id) } }.into()
+}
+
+#[component(path = "/jobs/{id}/done")]
+async fn job_done(_state: JobState, _url: UrlBuilder, Path(id): Path<u32>) -> Html {
+    Html::new(html! { div { "done " (id) } }).stop_polling()
+}
+
+fn poll_url(body: &str) -> String {
+    let start = body.find("hx-get=\"").expect("polling attributes") + "hx-get=\"".len();
+    body[start..][..body[start..].find('"').unwrap()].replace("&amp;", "&")
+}
+
+#[tokio::test]
+async fn polls_the_concrete_path_of_a_parametric_component() {
+    let app = htmoxide::app().htmx();
+    let response = common::hx_get(&app, "/jobs/7/status?tab=log").await;
+
+    assert_eq!(response.status, 200);
+    assert!(
+        response
+            .body
+            .starts_with(r#"<div id="job" hx-get="/jobs/7/status?tab=log""#)
+    );
+    assert!(response.body.contains(r#"hx-trigger="every 2s""#));
+    assert!(response.body.contains(r#"hx-swap="outerHTML""#));
+}
+
+#[tokio::test]
+async fn polls_under_the_base_path() {
+    let app = htmoxide::app().htmx().base_path("/tools");
+    let response = common::hx_get(&app, "/tools/jobs/7/status").await;
+
+    assert_eq!(poll_url(&response.body), "/tools/jobs/7/status");
+}
+
+#[tokio::test]
+async fn backs_off_while_the_markup_is_unchanged() {
+    let app = htmoxide::app().htmx();
+    let mut url = "/jobs/3/log".to_string();
+    let mut triggers = Vec::new();
+    for _ in 0..4 {
+        let response = common::hx_get(&app, &url).await;
+        let trigger = response.body.split("hx-trigger=\"").nth(1).unwrap();
+        triggers.push(trigger[..trigger.find('"').unwrap()].to_string());
+        url = poll_url(&response.body);
+        assert!(url.starts_with("/jobs/3/log?_poll="));
+    }
+    assert_eq!(triggers, ["every 1s", "every 2s", "every 4s", "every 4s"]);
+}
+
+#[tokio::test]
+async fn stopping_responds_with_286_without_polling_attributes() {
+    let app = htmoxide::app().htmx();
+    let response = common::hx_get(&app, "/jobs/7/done").await;
+
+    assert_eq!(response.status.as_u16(), 286);
+    assert_eq!(response.body, "<div>done 7</div>");
+}
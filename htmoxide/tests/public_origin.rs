@@ -0,0 +1,53 @@
+mod common;
+
+use htmoxide::PublicOrigin;
+use htmoxide::prelude::*;
+
+#[derive(Deserialize, Serialize, Default)]
+struct ShareState {
+    id: u32,
+}
+
+#[component(path = "/share")]
+async fn share(_state: ShareState, url: UrlBuilder) -> Html {
+    html! { (url.absolute().build()) }.into()
+}
+
+const HOST: (&str, &str) = ("host", "evil.example");
+
+#[tokio::test]
+async fn stays_relative_without_a_public_origin() {
+    let app = htmoxide::app().htmx();
+    let response = common::get(&app, "/share?id=7", &[HOST]).await;
+
+    assert_eq!(response.body, "/share?id=7");
+}
+
+#[tokio::test]
+async fn uses_the_configured_origin() {
+    let app = htmoxide::app()
+        .htmx()
+        .public_origin("https://todo.example.com/");
+    let response = common::get(&app, "/share?id=7", &[HOST]).await;
+
+    assert_eq!(response.body, "https://todo.example.com/share?id=7");
+}
+
+#[tokio::test]
+async fn trusts_forwarded_headers_when_opted_in() {
+    let app = htmoxide::app()
+        .htmx()
+        .public_origin(PublicOrigin::Forwarded);
+    let response = common::get(
+        &app,
+        "/share?id=7",
+        &[
+            HOST,
+            ("x-forwarded-proto", "https"),
+            ("x-forwarded-host", "todo.example.com"),
+        ],
+    )
+    .await;
+
+    assert_eq!(response.body, "https://todo.example.com/share?id=7");
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use htmoxide::prelude::*;
+use htmoxide::{Signed, tower_cookies::Key};
+
+#[derive(Deserialize, Serialize, Default)]
+struct InviteState {
+    team: u32,
+}
+
+#[component(path = "/invite")]
+async fn invite(
+    _state: InviteState,
+    _url: UrlBuilder,
+    Signed(invite): Signed<InviteState>,
+) -> Html {
+    html! { "team " (invite.team) }.into()
+}
+
+#[component(path = "/invite/accept")]
+async fn accept(
+    _state: InviteState,
+    _url: UrlBuilder,
+    Signed(invite): Signed<InviteState>,
+) -> Html {
+    html! { "joined " (invite.team) }.into()
+}
+
+#[component(path = "/grant")]
+async fn grant(_state: InviteState, _url: UrlBuilder, Signed(invite): Signed<InviteState>) -> Html {
+    html! { "granted " (invite.team) }.into()
+}
+
+fn invite_link(key: &Key) -> String {
+    UrlBuilder::new("/invite", "")
+        .with_state(&InviteState { team: 7 })
+        .signed(key, None)
+        .build()
+}
+
+#[tokio::test]
+async fn accepts_links_under_the_base_path() {
+    let key = Key::generate();
+    let app = htmoxide::app()
+        .htmx()
+        .signing_key(key.clone())
+        .base_path("/tools");
+    let response = common::hx_get(&app, &format!("/tools{}", invite_link(&key))).await;
+
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, "team 7");
+}
+
+#[tokio::test]
+async fn rejects_links_replayed_on_another_route() {
+    let key = Key::generate();
+    let app = htmoxide::app().htmx().signing_key(key.clone());
+    let link = invite_link(&key).replace("/invite", "/grant");
+    let response = common::hx_get(&app, &link).await;
+
+    assert_eq!(response.status, 403);
+    assert_eq!(response.body, "link signature is invalid");
+}
+
+#[tokio::test]
+async fn accepts_routes_starting_with_the_base_path() {
+    let key = Key::generate();
+    let app = htmoxide::app()
+        .htmx()
+        .signing_key(key.clone())
+        .base_path("/invite");
+    let link = UrlBuilder::new("/invite/accept", "")
+        .with_state(&InviteState { team: 7 })
+        .signed(&key, None)
+        .build();
+    let response = common::hx_get(&app, &format!("/invite{}", link)).await;
+
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, "joined 7");
+}
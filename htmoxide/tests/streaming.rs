@@ -0,0 +1,82 @@
+mod common;
+
+use axum::{body::Body, http::Request};
+use futures_util::StreamExt;
+use htmoxide::prelude::*;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tower::ServiceExt;
+
+static FEED_READY: Notify = Notify::const_new();
+static WIDGET_READY: Notify = Notify::const_new();
+
+#[derive(Deserialize, Serialize, Default)]
+struct WidgetState {
+    tab: String,
+}
+
+#[page("/dashboard")]
+async fn dashboard(ctx: RequestContext) -> StreamingPage {
+    let mut page = StreamingPage::new(&ctx);
+    let feed = page.defer(html! { "Loading feed" }, async {
+        FEED_READY.notified().await;
+        html! { "feed" }
+    });
+    page.body(html! { body { h1 { "Dashboard" } (feed) } })
+}
+
+#[component(path = "/widget", root = "widget", poll = "2s")]
+async fn widget(_state: WidgetState, _url: UrlBuilder, ctx: RequestContext) -> StreamingPage {
+    let mut page = StreamingPage::new(&ctx);
+    let slot = page.defer(html! { "Loading widget" }, async {
+        WIDGET_READY.notified().await;
+        html! { "widget" }
+    });
+    page.body(html! { div #widget { (slot) } })
+}
+
+/// Body chunks of a page, with the first one read before `ready` is notified
+async fn chunks(uri: &str, ready: &Notify) -> (String, String) {
+    let app = htmoxide::app().htmx();
+    let request = Request::get(uri)
+        .header("accept", "text/html")
+        .body(Body::empty())
+        .unwrap();
+    // Buffering the body would wait for `ready` forever
+    let (first, mut body) = tokio::time::timeout(Duration::from_secs(5), async {
+        let response = app.oneshot(request).await.unwrap();
+        let mut body = response.into_body().into_data_stream();
+        (body.next().await.unwrap().unwrap(), body)
+    })
+    .await
+    .expect("first chunk before the slots finish");
+    ready.notify_one();
+    let mut rest = Vec::new();
+    while let Some(chunk) = body.next().await {
+        rest.extend_from_slice(&chunk.unwrap());
+    }
+    (
+        String::from_utf8(first.to_vec()).unwrap(),
+        String::from_utf8(rest).unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn sends_the_page_before_deferred_slots_finish() {
+    let (first, rest) = chunks("/dashboard", &FEED_READY).await;
+
+    assert!(first.contains("<h1>Dashboard</h1>"));
+    assert!(first.contains("Loading feed"));
+    assert!(!first.contains(">feed<"));
+    assert!(rest.contains(r#"<div hidden id="htmoxide-fill-0">feed</div>"#));
+    assert!(rest.ends_with("</html>"));
+}
+
+#[tokio::test]
+async fn component_wrappers_pass_streamed_responses_through() {
+    let (first, rest) = chunks("/widget", &WIDGET_READY).await;
+
+    assert!(first.contains("Loading widget"));
+    assert!(!first.contains("hx-trigger"));
+    assert!(rest.contains(r#"<div hidden id="htmoxide-fill-0">widget</div>"#));
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use htmoxide::prelude::*;
+use std::collections::HashMap;
+
+fn migrate_todo_state(from: u32, params: &mut HashMap<String, String>) {
+    if from < 2 && params.get("status").map(String::as_str) == Some("done") {
+        params.insert("status".into(), "completed".into());
+    }
+}
+
+#[view_state(version = 2, migrate = migrate_todo_state)]
+#[derive(Deserialize, Serialize, Default)]
+struct TodoState {
+    #[view_state(alias = "filter")]
+    #[serde(default)]
+    status: String,
+}
+
+#[view_state(version = 2)]
+#[derive(Deserialize, Serialize, Default)]
+struct InviteState {
+    #[view_state(alias = "old")]
+    #[serde(default)]
+    new: String,
+}
+
+#[view_state(version = 2)]
+#[derive(Deserialize, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct SortState {
+    #[view_state(alias = "order")]
+    #[serde(default)]
+    sort_by: String,
+}
+
+#[component(path = "/sorted")]
+async fn sorted(state: SortState, _url: UrlBuilder) -> Html {
+    html! { (state.sort_by) }.into()
+}
+
+#[component(path = "/todos/done", method = "POST")]
+async fn mark_done(state: TodoState, _url: UrlBuilder) -> Html {
+    html! { (state.status) }.into()
+}
+
+#[component(path = "/invite/v2")]
+async fn invite(state: InviteState, _url: UrlBuilder) -> Html {
+    html! { (state.new) }.into()
+}
+
+#[component(path = "/todos")]
+async fn todos(state: TodoState, _url: UrlBuilder) -> Html {
+    html! { (state.status) }.into()
+}
+
+#[page("/reports")]
+async fn reports(ctx: RequestContext) -> Page {
+    html! { body { (ctx.query()) } }.into()
+}
+
+fn app() -> axum::Router {
+    htmoxide::app().with_state_urls().htmx()
+}
+
+#[tokio::test]
+async fn redirects_old_links_of_routed_states() {
+    let response = common::get(&app(), "/todos?filter=done", &[("accept", "text/html")]).await;
+
+    assert_eq!(response.status, 303);
+    assert_eq!(response.headers["location"], "/todos?_v=2&status=completed");
+}
+
+#[tokio::test]
+async fn leaves_unversioned_params_as_current() {
+    let response = common::get(&app(), "/todos?status=done", &[("accept", "text/html")]).await;
+
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, "done");
+}
+
+#[tokio::test]
+async fn leaves_states_of_other_routes_alone() {
+    let response = common::get(&app(), "/reports?filter=done", &[("accept", "text/html")]).await;
+
+    assert_eq!(response.status, 200);
+}
+
+#[tokio::test]
+async fn redirects_within_routes_starting_with_the_base_path() {
+    let app = htmoxide::app()
+        .with_state_urls()
+        .htmx()
+        .base_path("/invite");
+    let response = common::get(&app, "/invite/invite/v2?old=x", &[("accept", "text/html")]).await;
+
+    assert_eq!(response.status, 303);
+    assert_eq!(response.headers["location"], "/invite/invite/v2?_v=2&new=x");
+}
+
+#[tokio::test]
+async fn redirects_to_serde_renamed_fields() {
+    let response = common::get(&app(), "/sorted?order=name", &[("accept", "text/html")]).await;
+
+    assert_eq!(response.status, 303);
+    assert_eq!(response.headers["location"], "/sorted?_v=2&sortBy=name");
+}
+
+#[tokio::test]
+async fn leaves_old_links_of_form_posts_alone() {
+    let response = common::post_form(&app(), "/todos/done?filter=done", "").await;
+
+    assert_eq!(response.status, 200);
+}